@@ -0,0 +1,85 @@
+//! Derive macros for esm-parser field payloads.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{parse_macro_input, Data, DeriveInput, LitInt, Type};
+
+//------------------------------------------------------------------------------
+
+/// Derive `EsmField`, decoding the members in declaration order as packed
+/// little endian values.
+///
+/// Members of type `Option<T>` must be trailing and are `None` when the field
+/// ends before them. `#[esm(since = 15)]` on an optional member only reads it
+/// from records of that form version on.
+#[proc_macro_derive(EsmField, attributes(esm))]
+pub fn derive_esm_field(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(&input.ident, "EsmField can only be derived for structs"))
+    };
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    let mut optional = false;
+    for (i, field) in data.fields.iter().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => ident.to_token_stream(),
+            None => syn::Index::from(i).to_token_stream()
+        };
+        let since = since(field)?;
+        if is_option(&field.ty) {
+            optional = true;
+        } else if optional {
+            return Err(syn::Error::new_spanned(field, "optional members must be trailing"))
+        } else if since.is_some() {
+            return Err(syn::Error::new_spanned(field, "version dependent members must be optional"))
+        }
+        reads.push(match since {
+            Some(version) => quote! { #member: reader.since(#version)? },
+            None => quote! { #member: reader.read()? }
+        });
+        writes.push(quote! { ::esm_parser::field::EsmField::write(&self.#member, out); });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::esm_parser::field::EsmField for #name #ty_generics #where_clause {
+            fn read(reader: &mut ::esm_parser::field::FieldReader) -> ::esm_parser::Result<Self> {
+                Ok(Self { #(#reads),* })
+            }
+
+            fn write(&self, out: &mut Vec<u8>) {
+                #(#writes)*
+            }
+        }
+    })
+}
+
+/// Form version from `#[esm(since = N)]`.
+fn since(field: &syn::Field) -> syn::Result<Option<LitInt>> {
+    let mut since = None;
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("esm")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("since") {
+                since = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `since = <form version>`"))
+            }
+        })?;
+    }
+    Ok(since)
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.qself.is_none() && path.path.segments.last().is_some_and(|segment| segment.ident == "Option"),
+        _ => false
+    }
+}
@@ -0,0 +1,173 @@
+//! Asynchronous parsing, enabled by the `async` feature.
+
+use crate::decoders::Decoders;
+use crate::encoding::Encoding;
+use crate::error::{Error, Result};
+use crate::game::GameKind;
+use crate::model::*;
+use crate::strings::Strings;
+use crate::{ESMParser, Limits, Options};
+
+use std::io::SeekFrom;
+use std::sync::Arc;
+
+use futures_util::stream::{self, Stream};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+//------------------------------------------------------------------------------
+
+/// Parser reading from an asynchronous source.
+///
+/// Each record is read into memory without blocking and then decoded by the
+/// same field parsers as `ESMParser`.
+pub struct AsyncParser<R> {
+    reader: R,
+    options: Options
+}
+
+impl<R> AsyncParser<R> where R: AsyncRead + AsyncSeek + Unpin {
+    pub fn new(reader: R) -> Self {
+        AsyncParser { reader, options: Options::default() }
+    }
+
+    /// Select the game the plugin was made for, overriding detection.
+    pub fn set_game(&mut self, game: GameKind) {
+        self.options.game = Some(game);
+        Arc::make_mut(&mut self.options.decoders).set_game(game);
+    }
+
+    /// Decode strings with the given codepage.
+    pub fn set_encoding(&mut self, encoding: &'static Encoding) {
+        self.options.encoding = Some(encoding);
+    }
+
+    /// Use the string tables of a localised plugin.
+    pub fn set_strings(&mut self, strings: Strings) {
+        self.options.strings = Arc::new(strings);
+    }
+
+    /// Decode fields with registered decoders, see `Decoders`.
+    pub fn set_decoders(&mut self, mut decoders: Decoders) {
+        decoders.set_game(self.options.game.unwrap_or_default());
+        self.options.decoders = Arc::new(decoders);
+    }
+
+    /// Replace the size limits for records.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.options.limits = limits;
+    }
+
+    /// Skip malformed records instead of failing, see `ESMParser::set_lenient`.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.options.lenient = lenient;
+    }
+
+    /// Stream every record in file order, starting with the `TES4` header.
+    pub fn records(self) -> impl Stream<Item = Result<AsyncRecord>> {
+        let walk = Walk { parser: self, ends: Vec::new(), position: 0, end: None };
+        stream::try_unfold(walk, |mut walk| async move {
+            Ok(walk.next().await?.map(|record| (record, walk)))
+        })
+    }
+}
+
+/// Record yielded by `AsyncParser::records`.
+#[derive(Debug)]
+pub struct AsyncRecord {
+    pub record: Record,
+    /// Problems found decoding the record, see `ESMParser::diagnostics`.
+    pub diagnostics: Vec<Error>
+}
+
+/// Position in the group tree between records.
+struct Walk<R> {
+    parser: AsyncParser<R>,
+    /// End offsets of the groups entered.
+    ends: Vec<u64>,
+    position: u64,
+    /// File size, known after `start`.
+    end: Option<u64>
+}
+
+impl<R> Walk<R> where R: AsyncRead + AsyncSeek + Unpin {
+    /// Learn the file size and detect the game.
+    async fn start(&mut self) -> Result<u64> {
+        let AsyncParser { reader, options } = &mut self.parser;
+        let end = reader.seek(SeekFrom::End(0)).await?;
+        reader.seek(SeekFrom::Start(0)).await?;
+        if options.game.is_none() {
+            let mut head = Vec::with_capacity(GameKind::DETECT_SIZE);
+            (&mut *reader).take(GameKind::DETECT_SIZE as u64).read_to_end(&mut head).await?;
+            let game = GameKind::detect(&head)?;
+            options.game = Some(game);
+            Arc::make_mut(&mut options.decoders).set_game(game);
+            reader.seek(SeekFrom::Start(0)).await?;
+        }
+        self.end = Some(end);
+        Ok(end)
+    }
+
+    async fn next(&mut self) -> Result<Option<AsyncRecord>> {
+        let end = match self.end {
+            Some(end) => end,
+            None => self.start().await?
+        };
+        let AsyncParser { reader, options } = &mut self.parser;
+        let header_size = options.game.unwrap_or_default().header_size();
+        loop {
+            while self.ends.last() == Some(&self.position) { self.ends.pop(); }
+            if self.position >= end {
+                if self.ends.is_empty() { return Ok(None) }
+                return Err(Error::invalid("group extends past the end of the file").at(self.position))
+            }
+
+            let offset = self.position;
+            let mut buffer = vec![0; header_size];
+            reader.read_exact(&mut buffer).await?;
+            let header = { // no parser is held across the reads, so the stream stays `Send`
+                let mut parser = ESMParser::cursor(&buffer);
+                parser.game = options.game;
+                parser.read_header()?
+            };
+            if header.type_id == b"GRUP" { // only the records are yielded
+                if (header.size as usize) < header_size {
+                    return Err(Error::invalid("group smaller than its header").at(offset))
+                }
+                let group_end = offset + header.size as u64;
+                if group_end > self.ends.last().copied().unwrap_or(end) {
+                    return Err(Error::invalid("entry extends past the end of its group").at(offset))
+                }
+                self.ends.push(group_end);
+                self.position += header_size as u64;
+                continue
+            }
+
+            let size = header.size as u64;
+            let (what, limit) = match (header.flags & 0x00040000) != 0 {
+                true => ("compressed record", options.limits.compressed),
+                false => ("record", options.limits.inflated)
+            };
+            if size > limit { return Err(Error::limit(what, size, limit).at(offset)) }
+            if offset + (header_size as u64) + size > self.ends.last().copied().unwrap_or(end) { // before allocating
+                return Err(Error::invalid("entry extends past the end of its group").at(offset))
+            }
+            buffer.resize(header_size + size as usize, 0);
+            reader.read_exact(&mut buffer[header_size..]).await?;
+            self.position += buffer.len() as u64;
+
+            let mut parser = ESMParser::cursor(&buffer);
+            parser.apply(options.clone());
+            let mut builder = RecordBuilder::default();
+            let f = if header.type_id == b"TES4" { ESMParser::TES4 } else { ESMParser::GRUP };
+            let relocate = |mut e: Error| {
+                let context = e.context_mut();
+                context.offset = context.offset.map(|position| offset + position); // buffer to file position
+                e
+            };
+            parser.parse_records(f, buffer.len() as u64, &mut builder).map_err(relocate)?;
+            options.localised = parser.options().localised; // set by the TES4 header
+            let diagnostics = parser.diagnostics.drain(..).map(relocate).collect();
+            return Ok(Some(AsyncRecord { record: builder.finish()?, diagnostics }))
+        }
+    }
+}
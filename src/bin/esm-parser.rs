@@ -4,15 +4,68 @@ use esm_parser::prelude::*;
 
 fn main() -> esm_parser::Result<()> {
     // parse args
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    let lenient = args.iter().any(|arg| arg == "--lenient");
+    args.retain(|arg| arg != "--lenient");
+    let language = take_option(&mut args, "--language").unwrap_or_else(|| String::from("english"));
+    let game = take_option(&mut args, "--game").map(|name| name.parse::<GameKind>()).transpose()?;
+    let encoding = take_option(&mut args, "--encoding")
+        .map(|label| Encoding::for_label(label.as_bytes()).ok_or(Error::invalid("unknown encoding")))
+        .transpose()?;
     if args.len() < 2 {
-        println!("Usage: {} <file_path>", args[0]);
+        println!("Usage: {} [--lenient] [--language <language>] [--game <game>] [--encoding <codepage>] <file_path | ->", args[0]);
+        println!("Games: oblivion, fo3, fnv, skyrim, skyrimse, fo4");
         return Ok(())
     }
 
-    // parse file using guesser
-    let mut parser = ESMParser::file(&args[1])?;
-    parser.parse_top_level(ESMParser::TES4)
+    // parse file using guesser, or stdin when the path is '-'
+    if args[1] == "-" {
+        let parser = ESMParser::stream(std::io::stdin().lock());
+        dump(parser, lenient, game, encoding, None)
+    } else {
+        let parser = ESMParser::file(&args[1])?;
+        dump(parser, lenient, game, encoding, Some((&args[1], &language)))
+    }
+}
+
+/// Whether the `TES4` header flags the plugin as localised, only localised
+/// plugins ship string tables.
+fn localised(path: &str) -> esm_parser::Result<bool> {
+    use std::io::Read;
+    let mut header = [0u8; 12];
+    std::fs::File::open(path)?.read_exact(&mut header)?;
+    Ok(u32::from_le_bytes([header[8], header[9], header[10], header[11]]) & 0x00000080 != 0)
+}
+
+/// Configure the parser and dump the plugin to stdout, with the string tables
+/// of the plugin file at `path` in `language` if given.
+fn dump<R>(mut parser: ESMParser<R>, lenient: bool, game: Option<GameKind>, encoding: Option<&'static Encoding>,
+    strings: Option<(&str, &str)>) -> esm_parser::Result<()> where R: std::io::Read + std::io::Seek {
+    match game {
+        Some(game) => parser.set_game(game),
+        None => { parser.detect_game()?; }
+    }
+    parser.set_lenient(lenient);
+    if let Some(encoding) = encoding { parser.set_encoding(encoding); }
+    if let Some((path, language)) = strings {
+        match parser.load_strings(path, language) {
+            Ok(()) => {},
+            Err(Error::Io { source, .. }) if source.kind() == std::io::ErrorKind::NotFound && !localised(path)? => {},
+            Err(error) => eprintln!("warning: string tables not loaded: {}", error) // localised strings print as missing
+        }
+    }
+    parser.dump()?;
+    for diagnostic in parser.diagnostics() {
+        eprintln!("skipped: {}", diagnostic);
+    }
+    Ok(())
+}
+
+/// Remove `name <value>` from the arguments, returning the value.
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let i = args.iter().position(|arg| arg == name)?;
+    args.remove(i);
+    (i < args.len()).then(|| args.remove(i))
 }
 
 #[cfg(test)]
@@ -26,4 +79,37 @@ mod tests {
         cmd.arg("data/Zeta.esm");
         cmd.assert().success();
     }
+
+    #[test]
+    fn zeta_game() {
+        let mut cmd = Command::cargo_bin("esm-parser").unwrap();
+        cmd.args(["--game", "fo3", "data/Zeta.esm"]);
+        cmd.assert().success();
+        let mut cmd = Command::cargo_bin("esm-parser").unwrap();
+        cmd.args(["--game", "morrowind", "data/Zeta.esm"]);
+        cmd.assert().failure();
+    }
+
+    #[test]
+    fn zeta_strings() {
+        let output = Command::cargo_bin("esm-parser").unwrap().arg("data/Zeta.esm").output().unwrap();
+        assert!(output.stderr.is_empty());
+
+        let mut plugin = std::fs::read("data/Zeta.esm").unwrap();
+        plugin.truncate(24 + u32::from_le_bytes(plugin[4..8].try_into().unwrap()) as usize); // TES4 only
+        plugin[8] |= 0x80; // localised
+        let path = std::env::temp_dir().join(format!("esm-parser-{}-Zeta.esm", std::process::id()));
+        std::fs::write(&path, plugin).unwrap();
+        let output = Command::cargo_bin("esm-parser").unwrap().arg(&path).output().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("warning: string tables not loaded: io error"));
+    }
+
+    #[test]
+    fn zeta_stdin() {
+        let mut cmd = Command::cargo_bin("esm-parser").unwrap();
+        cmd.arg("-").stdin(std::fs::File::open("data/Zeta.esm").unwrap());
+        cmd.assert().success();
+    }
 }
@@ -0,0 +1,89 @@
+//! Field decoders registered at runtime.
+
+use esm_bindings::fo3::*;
+
+use crate::encoding::Encoding;
+use crate::error::Result;
+use crate::game::GameKind;
+use crate::model::Value;
+use crate::strings::Strings;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+//------------------------------------------------------------------------------
+
+/// Field being decoded and the plugin settings its payload depends on.
+pub struct FieldContext<'a> {
+    /// Header of the field, payloads sized by a preceding `XXXX` field are
+    /// larger than `header.size`, which is zero.
+    pub header: &'a FieldHeader,
+    /// Form version of the record the field belongs to.
+    pub version: u16,
+    pub encoding: &'static Encoding,
+    /// String tables of a localised plugin, `None` if strings are stored in the
+    /// fields.
+    pub strings: Option<&'a Strings>
+}
+
+/// Decoder of a field payload, given without the field header.
+pub type Decoder = Arc<dyn Fn(&FieldContext, &[u8]) -> Result<Value> + Send + Sync>;
+
+/// Field decoders keyed by record and field type.
+///
+/// The built-in decoders of the game are the defaults, a registered decoder
+/// replaces the built-in one for its pair and removing it restores the default.
+/// Parsers keep the defaults at the game they parse.
+#[derive(Clone, Default)]
+pub struct Decoders {
+    game: GameKind,
+    registered: HashMap<([u8;4], [u8;4]), Decoder>
+}
+
+impl Decoders {
+    /// Registry with the built-in decoders of `game` as defaults.
+    pub fn new(game: GameKind) -> Self {
+        Decoders { game, registered: HashMap::new() }
+    }
+
+    /// Game of the built-in decoders.
+    pub fn game(&self) -> GameKind { self.game }
+
+    pub(crate) fn set_game(&mut self, game: GameKind) {
+        self.game = game;
+    }
+
+    /// Decode `field` of `record` with `decoder`, e.g. `(b"WEAP", b"DNAM")`.
+    pub fn register<F>(&mut self, record: &[u8;4], field: &[u8;4], decoder: F)
+    where F: Fn(&FieldContext, &[u8]) -> Result<Value> + Send + Sync + 'static {
+        self.registered.insert((*record, *field), Arc::new(decoder));
+    }
+
+    /// Go back to the built-in decoder, returning whether one was registered.
+    pub fn remove(&mut self, record: &[u8;4], field: &[u8;4]) -> bool {
+        self.registered.remove(&(*record, *field)).is_some()
+    }
+
+    /// Decoder of `field` of `record`, the registered one or else the built-in
+    /// one. Built-in string decoders keep strings which do not decode as
+    /// `Value::RawString`, without the diagnostic the parser adds.
+    pub fn get(&self, record: &[u8;4], field: &[u8;4]) -> Option<Decoder> {
+        self.registered(record, field).cloned().or_else(|| Self::builtin(self.game, record, field))
+    }
+
+    /// Decoder registered for `field` of `record`, ignoring the defaults.
+    pub fn registered(&self, record: &[u8;4], field: &[u8;4]) -> Option<&Decoder> {
+        self.registered.get(&(*record, *field))
+    }
+
+    /// Built-in decoder of a game for `field` of `record`.
+    pub fn builtin(game: GameKind, record: &[u8;4], field: &[u8;4]) -> Option<Decoder> {
+        let schema = if record == b"TES4" { &crate::HEADER } else { game.schema() };
+        let kind = schema.fields(record).iter().find(|def| def.matches(field))?.kind;
+        Some(Arc::new(move |context: &FieldContext, data: &[u8]| kind.decode_bytes(context, data)))
+    }
+
+    /// Number of registered decoders, not counting the defaults.
+    pub fn len(&self) -> usize { self.registered.len() }
+    pub fn is_empty(&self) -> bool { self.registered.is_empty() }
+}
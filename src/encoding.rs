@@ -0,0 +1,44 @@
+//! Codepages of plugin strings.
+
+use crate::error::{Error, Result};
+use crate::game::GameKind;
+
+pub use encoding_rs::{Encoding, UTF_8, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252};
+
+//------------------------------------------------------------------------------
+
+/// Codepage of the official releases of a game for a string table language,
+/// Windows-1252 for English and unknown languages. Skyrim SE and Fallout 4
+/// store the other languages as UTF-8, the older games use the codepage of the
+/// language.
+pub fn default_encoding(game: GameKind, language: Option<&str>) -> &'static Encoding {
+    let language = language.map(str::to_ascii_lowercase);
+    match (game, language.as_deref()) {
+        (_, None | Some("english")) => WINDOWS_1252,
+        (GameKind::SkyrimSE | GameKind::Fallout4, Some(_)) => UTF_8,
+        (_, Some("polish" | "czech" | "hungarian")) => WINDOWS_1250,
+        (_, Some("russian")) => WINDOWS_1251,
+        (_, Some("japanese" | "chinese")) => UTF_8,
+        _ => WINDOWS_1252
+    }
+}
+
+/// Decode a zero terminated string, stripping the terminator.
+pub fn decode_zstring(bytes: &[u8], encoding: &'static Encoding) -> std::result::Result<String, &'static str> {
+    let Some((&0, string)) = bytes.split_last() else { return Err("string without a terminator") };
+    if string.contains(&0) { return Err("string with an embedded NUL") }
+    encoding.decode_without_bom_handling_and_without_replacement(string)
+        .map(|string| string.into_owned())
+        .ok_or("string is not valid in the plugin encoding")
+}
+
+/// Encode a string as a zero terminated plugin string, the inverse of
+/// `decode_zstring`.
+pub fn encode_zstring(string: &str, encoding: &'static Encoding) -> Result<Vec<u8>> {
+    if string.contains('\0') { return Err(Error::invalid("string with an embedded NUL")) }
+    let (bytes, _, unmappable) = encoding.encode(string);
+    if unmappable { return Err(Error::invalid("string not representable in the plugin encoding")) }
+    let mut bytes = bytes.into_owned();
+    bytes.push(0);
+    Ok(bytes)
+}
@@ -0,0 +1,185 @@
+//! Crate error type.
+
+use esm_bindings::fo3::*;
+
+use std::fmt;
+
+//------------------------------------------------------------------------------
+
+/// Step of the path from the top of the file to where an error occurred.
+#[derive(Debug, Clone, Copy)]
+pub enum Segment {
+    Group { label: [u8;4], group_type: i32 },
+    Record { type_id: [u8;4], id: formid_t },
+    Field { type_id: [u8;4] }
+}
+
+impl Segment {
+    pub fn group(header: &GroupHeader) -> Self {
+        let raw: [u8;24] = unsafe { std::mem::transmute(*header) };
+        let label = [raw[8], raw[9], raw[10], raw[11]];
+        let group_type = i32::from_le_bytes([raw[12], raw[13], raw[14], raw[15]]);
+        Segment::Group { label, group_type }
+    }
+
+    pub fn record(header: &RecordHeader) -> Self {
+        Segment::Record { type_id: header.type_id.0, id: header.id }
+    }
+
+    pub fn field(header: &FieldHeader) -> Self {
+        Segment::Field { type_id: header.type_id.0 }
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Group { label, group_type: 0 } => write!(f, "GRUP {}", String::from_utf8_lossy(label)),
+            Segment::Group { label, group_type } => write!(f, "GRUP {} {:#010x}", group_type, u32::from_le_bytes(*label)),
+            Segment::Record { type_id, id } => write!(f, "{} {:#010x}", String::from_utf8_lossy(type_id), id),
+            Segment::Field { type_id } => write!(f, "{}", String::from_utf8_lossy(type_id))
+        }
+    }
+}
+
+/// Path through groups, records and fields, e.g. `GRUP WEAP > WEAP 0x00012345 > DNAM`.
+#[derive(Debug, Clone, Default)]
+pub struct Path(pub Vec<Segment>);
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            if i > 0 { f.write_str(" > ")?; }
+            segment.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where in the file an error occurred.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    /// File offset of the header of the innermost segment of `path`. Fields of
+    /// compressed records have no file offset and are located at their record.
+    /// Absent until the error passes through the parser.
+    pub offset: Option<u64>,
+    pub path: Path
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(offset) = self.offset { write!(f, " at {:#x}", offset)?; }
+        if !self.path.0.is_empty() { write!(f, " in {}", self.path)?; }
+        Ok(())
+    }
+}
+
+//------------------------------------------------------------------------------
+
+/// Error raised while parsing or writing a plugin.
+#[derive(Debug)]
+pub enum Error {
+    /// A decoder consumed a different number of bytes than the header declared.
+    Size { context: Context, expected: u64, consumed: u64 },
+    /// Reading or writing the underlying stream failed.
+    Io { context: Context, source: std::io::Error },
+    /// A compressed record could not be inflated or deflated.
+    Zlib { context: Context, source: std::io::Error },
+    /// A compressed record exceeds a configured limit.
+    Limit { context: Context, what: &'static str, size: u64, limit: u64 },
+    /// A record inflated to a different size than it declared.
+    Inflated { context: Context, declared: u64, inflated: u64 },
+    /// Error raised by the chunk parser.
+    Parser { context: Context, source: chunk_parser::Error },
+    /// The data does not have the expected structure.
+    Invalid { context: Context, reason: &'static str },
+    /// A plugin was loaded before one of its masters.
+    Master { context: Context, plugin: String, master: String }
+}
+
+impl Error {
+    pub fn size(expected: u64, consumed: u64) -> Self {
+        Error::Size { context: Context::default(), expected, consumed }
+    }
+
+    pub fn invalid(reason: &'static str) -> Self {
+        Error::Invalid { context: Context::default(), reason }
+    }
+
+    pub fn limit(what: &'static str, size: u64, limit: u64) -> Self {
+        Error::Limit { context: Context::default(), what, size, limit }
+    }
+
+    pub fn inflated(declared: u64, inflated: u64) -> Self {
+        Error::Inflated { context: Context::default(), declared, inflated }
+    }
+
+    pub fn master(plugin: &str, master: &str) -> Self {
+        Error::Master { context: Context::default(), plugin: plugin.to_owned(), master: master.to_owned() }
+    }
+
+    pub fn zlib(source: std::io::Error) -> Self {
+        Error::Zlib { context: Context::default(), source }
+    }
+
+    /// Set the file offset of an error raised outside of the parser.
+    pub(crate) fn at(mut self, offset: u64) -> Self {
+        self.context_mut().offset = Some(offset);
+        self
+    }
+
+    pub fn context(&self) -> &Context {
+        match self {
+            Error::Size { context, .. } | Error::Io { context, .. } | Error::Zlib { context, .. } |
+            Error::Limit { context, .. } | Error::Inflated { context, .. } |
+            Error::Parser { context, .. } | Error::Invalid { context, .. } | Error::Master { context, .. } => context
+        }
+    }
+
+    pub fn context_mut(&mut self) -> &mut Context {
+        match self {
+            Error::Size { context, .. } | Error::Io { context, .. } | Error::Zlib { context, .. } |
+            Error::Limit { context, .. } | Error::Inflated { context, .. } |
+            Error::Parser { context, .. } | Error::Invalid { context, .. } | Error::Master { context, .. } => context
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Size { expected, consumed, .. } => write!(f, "expected {} bytes, consumed {}", expected, consumed)?,
+            Error::Io { source, .. } => write!(f, "io error: {}", source)?,
+            Error::Zlib { source, .. } => write!(f, "zlib error: {}", source)?,
+            Error::Limit { what, size, limit, .. } => write!(f, "{} of {} bytes exceeds the limit of {}", what, size, limit)?,
+            Error::Inflated { declared, inflated, .. } => write!(f, "declared {} inflated bytes, got {}", declared, inflated)?,
+            Error::Parser { source, .. } => write!(f, "parse error: {:?}", source)?,
+            Error::Invalid { reason, .. } => f.write_str(reason)?,
+            Error::Master { plugin, master, .. } => write!(f, "{} requires {}, which is not loaded before it", plugin, master)?
+        }
+        self.context().fmt(f)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } | Error::Zlib { source, .. } => Some(source),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(source: std::io::Error) -> Self {
+        Error::Io { context: Context::default(), source }
+    }
+}
+
+impl From<chunk_parser::Error> for Error {
+    fn from(source: chunk_parser::Error) -> Self {
+        Error::Parser { context: Context::default(), source }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
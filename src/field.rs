@@ -0,0 +1,145 @@
+//! Field payloads decoded from plain structs, see `#[derive(EsmField)]`.
+
+use crate::error::{Error, Result};
+use crate::model::Value;
+
+pub use esm_parser_derive::EsmField;
+
+//------------------------------------------------------------------------------
+
+/// Payload decoded member by member as packed little endian values.
+pub trait EsmField: Sized {
+    fn read(reader: &mut FieldReader) -> Result<Self>;
+
+    fn write(&self, out: &mut Vec<u8>);
+
+    /// Decode a whole field payload of a record with the given form version,
+    /// failing unless the layout consumes it exactly.
+    fn decode(data: &[u8], version: u16) -> Result<Self> {
+        let mut reader = FieldReader::new(data, version);
+        let value = Self::read(&mut reader)?;
+        match reader.remaining() {
+            0 => Ok(value),
+            _ => Err(Error::size(data.len() as u64, reader.position as u64))
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write(&mut out);
+        out
+    }
+}
+
+/// Cursor over a field payload.
+pub struct FieldReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    version: u16
+}
+
+impl<'a> FieldReader<'a> {
+    pub fn new(data: &'a [u8], version: u16) -> Self {
+        FieldReader { data, position: 0, version }
+    }
+
+    /// Form version of the record the field belongs to.
+    pub fn version(&self) -> u16 { self.version }
+
+    pub fn remaining(&self) -> usize { self.data.len() - self.position }
+
+    pub fn read<T: EsmField>(&mut self) -> Result<T> { T::read(self) }
+
+    /// Read a member added in form version `version`, absent from older records.
+    pub fn since<T: EsmField>(&mut self, version: u16) -> Result<Option<T>> {
+        match self.version >= version {
+            true => self.read(),
+            false => Ok(None)
+        }
+    }
+
+    /// Next `N` bytes, failing if the field ends first.
+    pub fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let end = self.position + N;
+        let bytes = self.data.get(self.position..end).ok_or(Error::size(self.data.len() as u64, end as u64))?;
+        self.position = end;
+        Ok(bytes.try_into().unwrap())
+    }
+}
+
+macro_rules! esm_field_le {
+    ($($ty:ty),*) => {
+        $(impl EsmField for $ty {
+            fn read(reader: &mut FieldReader) -> Result<Self> { Ok(<$ty>::from_le_bytes(reader.take()?)) }
+            fn write(&self, out: &mut Vec<u8>) { out.extend_from_slice(&self.to_le_bytes()); }
+        })*
+    };
+}
+
+esm_field_le!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+impl<T: EsmField, const N: usize> EsmField for [T; N] {
+    fn read(reader: &mut FieldReader) -> Result<Self> {
+        let items = (0..N).map(|_| reader.read()).collect::<Result<Vec<T>>>()?;
+        Ok(items.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        self.iter().for_each(|item| item.write(out));
+    }
+}
+
+/// Trailing member, absent when the field ends before it.
+impl<T: EsmField> EsmField for Option<T> {
+    fn read(reader: &mut FieldReader) -> Result<Self> {
+        match reader.remaining() {
+            0 => Ok(None),
+            _ => reader.read().map(Some)
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        if let Some(value) = self { value.write(out); }
+    }
+}
+
+//------------------------------------------------------------------------------
+
+/// Derived payload held by `Value::Struct`.
+pub trait FieldValue: std::fmt::Debug + Send + Sync {
+    fn write_to(&self, out: &mut Vec<u8>);
+}
+
+impl<T> FieldValue for T where T: EsmField + std::fmt::Debug + Send + Sync {
+    fn write_to(&self, out: &mut Vec<u8>) { self.write(out) }
+}
+
+/// Decode a payload into a `Value`, as used by schemas.
+pub(crate) fn decode_value<T>(data: &[u8], version: u16) -> Result<Value>
+where T: EsmField + std::fmt::Debug + Send + Sync + 'static {
+    Ok(Value::Struct(Box::new(T::decode(data, version)?)))
+}
+
+//------------------------------------------------------------------------------
+
+/// `DATA` of `CONT`.
+#[derive(Debug, Clone, Copy, EsmField)]
+pub struct ContainerData {
+    pub flags: u8,
+    pub weight: f32
+}
+
+/// `ANAM` of `ASPC`.
+#[derive(Debug, Clone, Copy, EsmField)]
+pub struct AcousticSpace {
+    pub environment: u32
+}
+
+/// `DEST` header of destructible objects, followed by a `DSTD` per stage.
+#[derive(Debug, Clone, Copy, EsmField)]
+pub struct Destructible {
+    pub health: i32,
+    pub stages: u8,
+    pub flags: u8,
+    pub unknown: [u8; 2]
+}
@@ -0,0 +1,138 @@
+//! Form IDs and the master lists they are relative to.
+
+use esm_bindings::fo3::formid_t;
+
+use crate::error::{Error, Result};
+use crate::model::{Plugin, Value};
+
+use std::fmt;
+
+//------------------------------------------------------------------------------
+
+/// Form ID as stored in a plugin, the top byte indexes the plugin's masters
+/// and the rest is the object index within the plugin it refers to.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct FormId(pub formid_t);
+
+impl FormId {
+    pub fn new(master: u8, index: u32) -> Self {
+        FormId(((master as u32) << 24) | (index & 0x00FFFFFF))
+    }
+
+    /// Position of the plugin the ID refers to in the master list, the plugin
+    /// itself when it equals the number of masters.
+    pub fn master(self) -> u8 { (self.0 >> 24) as u8 }
+
+    pub fn index(self) -> u32 { self.0 & 0x00FFFFFF }
+}
+
+impl From<formid_t> for FormId {
+    fn from(id: formid_t) -> Self { FormId(id) }
+}
+
+impl From<FormId> for formid_t {
+    fn from(id: FormId) -> Self { id.0 }
+}
+
+impl fmt::Debug for FormId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:08X}", self.0)
+    }
+}
+
+impl fmt::Display for FormId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+//------------------------------------------------------------------------------
+
+/// Masters of a plugin, resolving the master byte of the plugin's form IDs.
+#[derive(Debug, Clone)]
+pub struct Masters {
+    masters: Vec<String>,
+    plugin: String
+}
+
+impl Masters {
+    /// Masters in `MAST` order of the plugin file named `plugin`.
+    pub fn new(masters: Vec<String>, plugin: impl Into<String>) -> Self {
+        Masters { masters, plugin: plugin.into() }
+    }
+
+    /// Read the `MAST` fields of a parsed plugin, named `name` in the load order.
+    /// Names which did not decode are kept lossily, so the masters after them
+    /// keep their position.
+    pub fn of(plugin: &Plugin, name: impl Into<String>) -> Self {
+        let masters = plugin.header.fields.iter()
+            .filter(|field| field.header.type_id == b"MAST")
+            .map(|field| match &field.value {
+                Value::ZString(master) => master.clone(),
+                value => {
+                    let mut bytes = Vec::new();
+                    value.encode(&mut bytes);
+                    String::from_utf8_lossy(bytes.strip_suffix(b"\0").unwrap_or(&bytes)).into_owned()
+                }
+            })
+            .collect();
+        Masters::new(masters, name)
+    }
+
+    pub fn masters(&self) -> &[String] { &self.masters }
+
+    /// File name of the plugin the masters belong to.
+    pub fn plugin(&self) -> &str { &self.plugin }
+
+    /// Plugin an ID refers to, `None` if its master byte is out of range.
+    pub fn resolve(&self, id: FormId) -> Option<&str> {
+        let master = id.master() as usize;
+        match master.cmp(&self.masters.len()) {
+            std::cmp::Ordering::Less => Some(&self.masters[master]),
+            std::cmp::Ordering::Equal => Some(&self.plugin),
+            std::cmp::Ordering::Greater => None
+        }
+    }
+
+    /// Print an ID with the plugin it refers to, e.g. `Fallout3.esm:0x00ABCD`.
+    pub fn display(&self, id: FormId) -> impl fmt::Display + '_ {
+        Resolved { plugin: self.resolve(id), id }
+    }
+
+    /// Replace the master byte by the load order position of the plugin the
+    /// ID refers to. Plugin names compare case insensitively.
+    pub fn to_global<S: AsRef<str>>(&self, id: FormId, load_order: &[S]) -> Result<FormId> {
+        let plugin = self.resolve(id).ok_or(Error::invalid("form ID refers to a missing master"))?;
+        let position = position(load_order, plugin).ok_or(Error::invalid("master not in the load order"))?;
+        let position = u8::try_from(position).map_err(|_| Error::invalid("load order of more than 255 plugins"))?;
+        Ok(FormId::new(position, id.index()))
+    }
+
+    /// Inverse of `to_global`, failing for IDs of plugins which are not masters
+    /// of this plugin.
+    pub fn from_global<S: AsRef<str>>(&self, id: FormId, load_order: &[S]) -> Result<FormId> {
+        let plugin = load_order.get(id.master() as usize).ok_or(Error::invalid("form ID outside the load order"))?;
+        let master = position(&self.masters, plugin.as_ref())
+            .or_else(|| self.plugin.eq_ignore_ascii_case(plugin.as_ref()).then_some(self.masters.len()))
+            .ok_or(Error::invalid("form ID of a plugin which is not a master"))?;
+        Ok(FormId::new(master as u8, id.index()))
+    }
+}
+
+fn position<S: AsRef<str>>(names: &[S], name: &str) -> Option<usize> {
+    names.iter().position(|candidate| candidate.as_ref().eq_ignore_ascii_case(name))
+}
+
+struct Resolved<'a> {
+    plugin: Option<&'a str>,
+    id: FormId
+}
+
+impl fmt::Display for Resolved<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.plugin {
+            Some(plugin) => write!(f, "{}:0x{:06X}", plugin, self.id.index()),
+            None => write!(f, "<missing master {:#04x}>:0x{:06X}", self.id.master(), self.id.index())
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Supported games and their record layouts.
+
+mod fo3;
+mod fo4;
+mod oblivion;
+mod skyrim;
+
+use crate::error::{Error, Result};
+use crate::schema::Schema;
+
+//------------------------------------------------------------------------------
+
+/// Game a plugin was made for, selecting the header size and field decoders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GameKind {
+    Oblivion,
+    #[default]
+    Fallout3,
+    FalloutNV,
+    SkyrimLE,
+    SkyrimSE,
+    Fallout4
+}
+
+impl GameKind {
+    /// Size of record and group headers, Oblivion lacks the trailing version
+    /// fields.
+    pub fn header_size(self) -> usize {
+        match self {
+            GameKind::Oblivion => 20,
+            _ => 24
+        }
+    }
+
+    /// Guess the game from the start of a plugin, using the header layout, the
+    /// `TES4` form version and the `HEDR` version.
+    pub fn detect(data: &[u8]) -> Result<Self> {
+        Self::guess(data).ok_or(match data.get(0..4) {
+            Some(b"TES3") => Error::invalid("Morrowind plugins are not supported"),
+            _ => Error::invalid("unrecognised plugin header")
+        })
+    }
+
+    fn guess(data: &[u8]) -> Option<Self> {
+        if data.get(0..4)? != b"TES4" { return None }
+        if data.get(20..24)? == b"HEDR" { return Some(GameKind::Oblivion) } // 20 byte header
+        if data.get(24..28)? != b"HEDR" { return None }
+        let form_version = u16::from_le_bytes(data.get(20..22)?.try_into().ok()?);
+        let version = f32::from_le_bytes(data.get(30..34)?.try_into().ok()?);
+        Some(match form_version {
+            131.. => GameKind::Fallout4,
+            44.. => GameKind::SkyrimSE,
+            40.. => GameKind::SkyrimLE,
+            _ if version >= 1.7 => GameKind::SkyrimSE, // form version not set by the tool
+            _ if version >= 1.3 => GameKind::FalloutNV,
+            _ if version == 0.95 => GameKind::Fallout4,
+            _ => GameKind::Fallout3
+        })
+    }
+
+    /// Number of bytes `detect` needs, the first record header and `HEDR`.
+    pub const DETECT_SIZE: usize = 34;
+
+    /// Record layouts of this game.
+    pub fn schema(self) -> &'static Schema {
+        match self {
+            GameKind::Oblivion => &oblivion::OBLIVION,
+            GameKind::Fallout3 | GameKind::FalloutNV => &fo3::FO3,
+            GameKind::SkyrimLE | GameKind::SkyrimSE => &skyrim::SKYRIM,
+            GameKind::Fallout4 => &fo4::FALLOUT4
+        }
+    }
+}
+
+/// Parse a game name as accepted on the command line, e.g. `fo3` or `skyrimse`.
+impl std::str::FromStr for GameKind {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Ok(match name.to_ascii_lowercase().as_str() {
+            "oblivion" | "tes4" => GameKind::Oblivion,
+            "fo3" | "fallout3" => GameKind::Fallout3,
+            "fnv" | "falloutnv" => GameKind::FalloutNV,
+            "skyrim" | "skyrimle" | "tes5" => GameKind::SkyrimLE,
+            "skyrimse" | "sse" => GameKind::SkyrimSE,
+            "fo4" | "fallout4" => GameKind::Fallout4,
+            _ => return Err(Error::invalid("unknown game"))
+        })
+    }
+}
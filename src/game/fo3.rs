@@ -0,0 +1,95 @@
+//! Fallout 3 and New Vegas record layouts.
+
+use crate::field::*;
+use crate::schema::{schema, Schema};
+
+//------------------------------------------------------------------------------
+
+/// Layouts of the records decoded with the FO3 bindings, New Vegas shares the
+/// layouts of every record listed here. Records such as `SCPT`, `NAVM` and
+/// `REFR` are not listed and keep all their fields raw.
+pub(crate) static FO3: Schema = schema! {
+    records {
+        GLOB { EDID: ZString, FNAM: U8, FLTV: F32 }
+        FACT {
+            EDID: ZString, FULL: LString, XNAM: [XNAM], DATA: U32, RNAM: [U32], MNAM: [LString], FNAM: [LString]
+        }
+        TXST { EDID: ZString, OBND: OBND, TX00..TX07: ZString, DNAM: U16 /*, DODT: DODT */ }
+        CLAS { EDID: ZString, FULL: LString, DESC: LString, /* ICON: ZString, */ DATA: CLAS, ATTR: ATTR }
+        SOUN { EDID: ZString, OBND: OBND, FNAM: ZString, SNDD: SNDD, SDSC: FormId }
+        ASPC { EDID: ZString, OBND: OBND, SNAM: [FormId], RDAT: FormId, BNAM: FormId, ANAM: Struct(AcousticSpace) }
+        MGEF { EDID: ZString, /* VMAD, */ FULL: LString, DESC: LString, /* MDOB: FormId, KSIZ: U32, KWDA, */ DATA: MGEF }
+        ENCH { EDID: ZString, FULL: LString, ENIT: ENIT, EFID: [FormId], EFIT: [EFIT] }
+        SPEL { EDID: ZString, FULL: LString, SPIT: SPIT, EFID: [FormId], EFIT: [EFIT], CTDA: [CTDA] }
+        ACTI {
+            EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, SCRI: FormId, DEST: Struct(Destructible),
+            DSTD: [DSTD], DMDL: [ZString], DSTF: [Empty], SNAM: FormId, VNAM: FormId
+        }
+        TERM {
+            EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, DESC: LString, SNAM: FormId,
+            ITXT: [ZString], RNAM: [ZString], SCHR: [SCHR], CTDA: [CTDA]
+        }
+        CONT {
+            EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, SCRI: FormId, CNTO: [CNTO], COED: [COED],
+            DATA: Struct(ContainerData)
+        }
+        LIGH { EDID: ZString, OBND: OBND, MODL: ZString, SCRI: FormId }
+        MISC { EDID: ZString, OBND: OBND, MODL: ZString, ICON: ZString }
+        STAT { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString }
+        MSTT { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, DATA: U8 }
+        PWAT { EDID: ZString, OBND: OBND, MODL: ZString }
+        FURN { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, MNAM: U32 }
+        WEAP {
+            EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, ICON: ZString, SCRI: FormId, EITM: FormId,
+            NAM0: FormId, REPL: FormId, ETYP: U32, YNAM: FormId, ZNAM: FormId, MOD2: ZString, MOD3: ZString,
+            MOD4: ZString, NNAM: ZString, INAM: FormId, WNAM: FormId, SNAM: [FormId], XNAM: FormId, TNAM: FormId,
+            NAM6: FormId, UNAM: FormId, NAM9: FormId, NAM8: FormId, DATA: DATA, DNAM: DNAM, CRDT: CRDT, VNAM: U32
+        }
+        AMMO { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, ICON: ZString }
+        CREA { EDID: ZString, OBND: OBND, FULL: LString }
+        LVLC { EDID: ZString, OBND: OBND }
+        ALCH {
+            EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, ICON: ZString, EFID: [FormId], EFIT: [EFIT],
+            CTDA: [CTDA]
+        }
+        NOTE { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, ICON: ZString }
+        PROJ { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString }
+        LVLI { EDID: ZString, OBND: OBND }
+        REGN { EDID: ZString }
+        DIAL { EDID: ZString }
+        QUST { EDID: ZString }
+        PACK { EDID: ZString }
+        CSTY { EDID: ZString }
+        ANIO { EDID: ZString, MODL: ZString }
+        WATR { EDID: ZString, FULL: LString }
+        EFSH { EDID: ZString, ICON: ZString, ICO2: ZString }
+        EXPL { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString }
+        DEBR { EDID: ZString }
+        IMGS { EDID: ZString }
+        FLST { EDID: ZString }
+        PERK { EDID: ZString, FULL: LString, DESC: LString, ICON: ZString }
+        BPTD { EDID: ZString, MODL: ZString }
+        ADDN { EDID: ZString, OBND: OBND, MODL: ZString }
+        CAMS { EDID: ZString }
+        CPTH { EDID: ZString, CTDA: [CTDA], MODL: ZString }
+        VTYP { EDID: ZString }
+        IPCT { EDID: ZString, MODL: ZString }
+        IPDS { EDID: ZString }
+        ARMA { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, MOD3: ZString }
+        ECZN { EDID: ZString }
+        MESG { EDID: ZString, DESC: LString, FULL: LString }
+        RGDL { EDID: ZString }
+        NPC_ { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString }
+        WRLD { EDID: ZString, FULL: LString, CNAM: FormId }
+        TACT { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, SCRI: FormId, VNAM: FormId }
+        ARMO {
+            EDID: ZString, OBND: OBND, FULL: LString, SCRI: FormId, EITM: FormId, MODL: ZString, MODS: ZString,
+            MOD2: ZString, MO2S: ZString, ICON: ZString, MOD3: ZString, MO3S: ZString
+        }
+        DOOR { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, SCRI: FormId }
+        SCOL { EDID: ZString, OBND: OBND, MODL: ZString }
+        IDLM { EDID: ZString, OBND: OBND }
+        CELL { EDID: ZString, FULL: LString }
+        ACRE { EDID: ZString }
+    }
+};
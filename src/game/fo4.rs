@@ -0,0 +1,24 @@
+//! Fallout 4 record layouts.
+
+use crate::schema::{schema, Schema};
+
+//------------------------------------------------------------------------------
+
+/// Layouts of the common Fallout 4 records. There are no Fallout 4 bindings,
+/// only the fields leading each record are listed, the rest stay raw.
+pub(crate) static FALLOUT4: Schema = schema! {
+    records {
+        GMST { EDID: ZString }
+        KYWD { EDID: ZString, CNAM: U32 }
+        GLOB { EDID: ZString, FNAM: U8, FLTV: F32 }
+        ACTI { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString }
+        CONT { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString }
+        LIGH { EDID: ZString, OBND: OBND, MODL: ZString, FULL: LString }
+        MISC { EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString }
+        STAT { EDID: ZString, OBND: OBND, MODL: ZString }
+        FLST { EDID: ZString, FULL: LString, LNAM: [FormId] }
+        CELL { EDID: ZString, FULL: LString }
+        MESG { EDID: ZString, DESC: LString, FULL: LString }
+    }
+    shared { EDID: ZString, FULL: LString, DESC: LString, OBND: OBND, MODL: ZString }
+};
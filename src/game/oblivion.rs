@@ -0,0 +1,46 @@
+//! Oblivion record layouts.
+
+use crate::schema::{schema, Schema};
+
+//------------------------------------------------------------------------------
+
+/// Layouts of the common Oblivion records. There are no Oblivion bindings, so
+/// fields whose size varies between versions, such as `CLAS` and `LIGH` `DATA`,
+/// are not listed and stay raw. Strings are never localised.
+pub(crate) static OBLIVION: Schema = schema! {
+    records {
+        GMST { EDID: ZString }
+        GLOB { EDID: ZString, FNAM: U8, FLTV: F32 }
+        CLAS { EDID: ZString, FULL: ZString, DESC: ZString, ICON: ZString }
+        FACT {
+            EDID: ZString, FULL: ZString, XNAM: [Bytes(8)], DATA: U8, CNAM: F32, RNAM: [U32], MNAM: [ZString],
+            FNAM: [ZString], INAM: [ZString]
+        }
+        HAIR { EDID: ZString, FULL: ZString, MODL: ZString, MODB: F32, ICON: ZString, DATA: U8 }
+        EYES { EDID: ZString, FULL: ZString, ICON: ZString, DATA: U8 }
+        SOUN { EDID: ZString, FNAM: ZString }
+        ACTI { EDID: ZString, FULL: ZString, MODL: ZString, MODB: F32, SCRI: FormId, SNAM: FormId }
+        CONT {
+            EDID: ZString, FULL: ZString, MODL: ZString, MODB: F32, SCRI: FormId, CNTO: [Bytes(8)], DATA: Bytes(5),
+            SNAM: FormId, QNAM: FormId
+        }
+        DOOR {
+            EDID: ZString, FULL: ZString, MODL: ZString, MODB: F32, SCRI: FormId, SNAM: FormId, ANAM: FormId,
+            BNAM: FormId, FNAM: U8, TNAM: [FormId]
+        }
+        LIGH {
+            EDID: ZString, MODL: ZString, MODB: F32, SCRI: FormId, FULL: ZString, ICON: ZString, FNAM: F32,
+            SNAM: FormId
+        }
+        MISC { EDID: ZString, FULL: ZString, MODL: ZString, MODB: F32, ICON: ZString, SCRI: FormId, DATA: Bytes(8) }
+        KEYM { EDID: ZString, FULL: ZString, MODL: ZString, MODB: F32, ICON: ZString, SCRI: FormId, DATA: Bytes(8) }
+        STAT { EDID: ZString, MODL: ZString, MODB: F32 }
+        FLOR {
+            EDID: ZString, FULL: ZString, MODL: ZString, MODB: F32, SCRI: FormId, PFIG: FormId, PFPC: Bytes(4)
+        }
+        FURN { EDID: ZString, FULL: ZString, MODL: ZString, MODB: F32, SCRI: FormId, MNAM: U32 }
+        CELL { EDID: ZString, FULL: ZString, DATA: U8 }
+        WRLD { EDID: ZString, FULL: ZString, WNAM: FormId, CNAM: FormId, NAM2: FormId, ICON: ZString }
+    }
+    shared { EDID: ZString, FULL: ZString, DESC: ZString, MODL: ZString, ICON: ZString, SCRI: FormId }
+};
@@ -0,0 +1,53 @@
+//! Skyrim record layouts.
+
+use crate::schema::{schema, Schema};
+
+//------------------------------------------------------------------------------
+
+/// Layouts of the common Skyrim records, shared by Legendary and Special
+/// Edition. There are no Skyrim bindings, so lists such as `KWDA` and fields
+/// with versioned layouts such as `DODT` and `SNDD` are not listed and stay raw.
+pub(crate) static SKYRIM: Schema = schema! {
+    records {
+        GMST { EDID: ZString }
+        KYWD { EDID: ZString, CNAM: U32 }
+        LCRT { EDID: ZString, CNAM: U32 }
+        AACT { EDID: ZString, CNAM: U32 }
+        TXST { EDID: ZString, OBND: OBND, TX00..TX07: ZString, DNAM: U16 }
+        GLOB { EDID: ZString, FNAM: U8, FLTV: F32 }
+        FACT { EDID: ZString, FULL: LString, XNAM: [Bytes(12)], DATA: U32 }
+        EYES { EDID: ZString, FULL: LString, ICON: ZString, DATA: U8 }
+        SOUN { EDID: ZString, OBND: OBND, FNAM: ZString, SDSC: FormId }
+        ASPC { EDID: ZString, OBND: OBND, SNAM: FormId, RDAT: FormId, BNAM: FormId }
+        ACTI {
+            EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, KSIZ: U32, PNAM: U32, SNAM: FormId,
+            VNAM: FormId, WNAM: FormId, RNAM: LString, FNAM: U16, KNAM: FormId
+        }
+        CONT {
+            EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, COCT: U32, CNTO: [Bytes(8)], DATA: Bytes(5),
+            SNAM: FormId, QNAM: FormId
+        }
+        BOOK {
+            EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, ICON: ZString, MICO: ZString, DESC: LString,
+            YNAM: FormId, ZNAM: FormId, KSIZ: U32, DATA: Bytes(16), INAM: FormId, CNAM: LString
+        }
+        LIGH {
+            EDID: ZString, OBND: OBND, MODL: ZString, FULL: LString, ICON: ZString, MICO: ZString, FNAM: F32,
+            SNAM: FormId
+        }
+        MISC {
+            EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, ICON: ZString, MICO: ZString, YNAM: FormId,
+            ZNAM: FormId, KSIZ: U32, DATA: Bytes(8)
+        }
+        KEYM {
+            EDID: ZString, OBND: OBND, FULL: LString, MODL: ZString, ICON: ZString, MICO: ZString, YNAM: FormId,
+            ZNAM: FormId, KSIZ: U32, DATA: Bytes(8)
+        }
+        STAT { EDID: ZString, OBND: OBND, MODL: ZString, DNAM: Bytes(8) }
+        FLST { EDID: ZString, LNAM: [FormId] }
+        CELL { EDID: ZString, FULL: LString }
+        WRLD { EDID: ZString, FULL: LString }
+        MESG { EDID: ZString, DESC: LString, FULL: LString, INAM: FormId }
+    }
+    shared { EDID: ZString, FULL: LString, DESC: LString, OBND: OBND, MODL: ZString }
+};
@@ -0,0 +1,32 @@
+//! FormID index for random access to records.
+
+use crate::form_id::FormId;
+
+use std::collections::HashMap;
+
+//------------------------------------------------------------------------------
+
+/// Location of a record in the file.
+#[derive(Debug, Clone, Copy)]
+pub struct IndexEntry {
+    pub id: FormId,
+    pub type_id: [u8;4],
+    /// Offset of the record header from the start of the file.
+    pub offset: u64,
+    pub compressed: bool
+}
+
+/// Record locations keyed by FormID.
+#[derive(Debug, Default)]
+pub struct Index(HashMap<FormId, IndexEntry>);
+
+impl Index {
+    pub fn get(&self, id: FormId) -> Option<&IndexEntry> { self.0.get(&id) }
+    pub fn len(&self) -> usize { self.0.len() }
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+    pub fn iter(&self) -> impl Iterator<Item = &IndexEntry> { self.0.values() }
+
+    pub(crate) fn insert(&mut self, entry: IndexEntry) {
+        self.0.insert(entry.id, entry);
+    }
+}
@@ -2,72 +2,199 @@
 
 #![allow(non_snake_case)]
 
+extern crate self as esm_parser; // paths generated by `#[derive(EsmField)]`
+
 use chunk_parser::prelude::*;
-pub use chunk_parser::Result;
 use esm_bindings::fo3::*;
 
-use std::ffi::CString;
 use std::io::Read;
+use std::sync::Arc;
 
 use flate2::read::ZlibDecoder;
 
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod decoders;
+use decoders::*;
+pub mod encoding;
+pub mod field;
+pub mod form_id;
+use form_id::FormId;
+use encoding::*;
+pub mod error;
+pub use error::{Error, Result};
+use error::*;
+pub mod game;
+use game::*;
+pub mod index;
+use index::*;
+pub mod load_order;
+pub mod mapped;
+pub mod parallel;
+pub mod schema;
+use schema::*;
+pub mod stream;
+pub mod model;
+use model::*;
+pub mod strings;
+use strings::*;
+pub mod visitor;
+use visitor::*;
+pub mod writer;
+
 //------------------------------------------------------------------------------
 
-#[chunk_parser(custom,depth)]
-pub struct ESMParser {
-    localised: bool
+/// Upper bounds for compressed records, which are read from untrusted headers.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// Largest zlib stream read into memory.
+    pub compressed: u64,
+    /// Largest record a zlib stream may inflate to.
+    pub inflated: u64
 }
 
-type RecordParser<P> = fn(parser: &mut P, header: &RecordHeader) -> Result<()>;
-type FieldParser<P> = fn(parser: &mut P, header: &FieldHeader) -> Result<()>;
+impl Default for Limits {
+    fn default() -> Self {
+        Limits { compressed: 64 << 20, inflated: 256 << 20 }
+    }
+}
 
-macro_rules! indent {
-    ($parser:expr, $($arg:tt)*) => {
-        let indent = " ".repeat($parser.depth() as usize * 2);
-        print!("{}{}", indent, format!($($arg)*));
-    };
+/// Settings inherited by the parsers of compressed records and parallel groups.
+#[derive(Clone, Default)]
+struct Options {
+    localised: bool,
+    lenient: bool,
+    strings: Arc<Strings>,
+    game: Option<GameKind>,
+    limits: Limits,
+    encoding: Option<&'static Encoding>,
+    decoders: Arc<Decoders>
 }
-macro_rules! indentln {
-    ($parser:expr, $($arg:tt)*) => {
-        let indent = " ".repeat($parser.depth() as usize * 2);
-        println!("{}{}", indent, format!($($arg)*));
-    };
+
+#[chunk_parser(custom,depth)]
+pub struct ESMParser {
+    localised: bool,
+    output: Sink,
+    index: Index,
+    /// Whether `index` was built, a plugin without records has an empty one.
+    indexed: bool,
+    path: Path,
+    /// Offsets of the segments of `path`.
+    starts: Vec<u64>,
+    lenient: bool,
+    /// Reading from a `Forward` stream, see `ESMParser::stream`.
+    forward: bool,
+    diagnostics: Vec<Error>,
+    strings: Arc<Strings>,
+    game: Option<GameKind>,
+    limits: Limits,
+    encoding: Option<&'static Encoding>,
+    oversized: Option<u32>,
+    decoders: Arc<Decoders>
 }
 
+
+/// Layout of the `TES4` header record, shared by every game.
+pub(crate) static HEADER: Schema = schema! {
+    records {
+        TES4 { HEDR: HEDR, CNAM: ZString, MAST: [ZString], DATA: [U64] /*, ONAM */ }
+    }
+};
+
 /// Elder Scrolls Master parser implementation.
 impl<R> ESMParser<R> where R: std::io::Read + std::io::Seek {
     /// Read a fixed sized string.
-    fn read_zstring(&mut self, length: u16) -> Result<CString> {
-        let mut v = Vec::with_capacity(length as usize);
-        unsafe {
-            let ptr = v.as_mut_ptr();
-            self.reader().read_exact(std::slice::from_raw_parts_mut(ptr, length as usize))?;
-            v.set_len(length as usize);
+    fn read_zstring(&mut self, length: usize) -> Result<Value> {
+        self.read_string(length, Value::ZString)
+    }
+
+    /// Read a zero terminated string, keeping the raw bytes with a diagnostic
+    /// if it does not decode.
+    fn read_string(&mut self, length: usize, variant: fn(String) -> Value) -> Result<Value> {
+        let bytes = self.read_bytes(length)?;
+        match decode_zstring(&bytes, self.encoding()) {
+            Ok(string) => Ok(variant(string)),
+            Err(reason) => {
+                let diagnostic = self.locate(Error::invalid(reason));
+                self.diagnostics.push(diagnostic);
+                Ok(Value::RawString(bytes))
+            }
         }
-        Ok(unsafe { CString::from_vec_unchecked(v) })
     }
 
-    /// Read a potentially localised string.
-    fn read_lstring(&mut self, length: u16) -> Result<CString> {
-        if self.localised { panic!("unimplemented lstring");  }
-        else { self.read_zstring(length) }
+    /// Read a potentially localised string, looking up the string tables for
+    /// localised plugins.
+    fn read_lstring(&mut self, header: &FieldHeader) -> Result<Value> {
+        if !self.localised { return self.read_string(self.field_size(header), Value::LString) }
+        let id: u32 = self.read()?;
+        let string = self.strings.get(StringKind::of(&header.type_id.0), id).cloned();
+        Ok(Value::Localised(LocalisedString { id, string }))
+    }
+
+    /// Read a raw byte buffer.
+    fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>> {
+        let mut v = vec![0; length];
+        self.reader().read_exact(&mut v)?;
+        Ok(v)
+    }
+
+    /// Read a record or group header in the layout of the current game.
+    fn read_header(&mut self) -> Result<RecordHeader> {
+        self.next_header()?.ok_or_else(|| std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into())
     }
 
-    /// Decompress a Zlib buffer.
-    fn deflate(&mut self, size: usize) -> Result<Vec<u8>> {
-        let mut v = Vec::with_capacity(size);
-        unsafe {
-            let ptr = v.as_mut_ptr();
-            self.reader().read_exact(std::slice::from_raw_parts_mut(ptr, size))?;
-            v.set_len(size);
+    /// Read a header, `None` at a clean end of the stream.
+    fn next_header(&mut self) -> Result<Option<RecordHeader>> {
+        let mut raw = [0u8; 24];
+        let size = self.layout().header_size();
+        let mut filled = 0;
+        while filled < size {
+            match self.reader().read(&mut raw[filled..size])? {
+                0 if filled == 0 => return Ok(None),
+                0 => return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()),
+                n => filled += n
+            }
+        }
+        Ok(Some(unsafe { std::mem::transmute::<[u8;24], RecordHeader>(raw) }))
+    }
+
+    fn options(&self) -> Options {
+        Options {
+            localised: self.localised,
+            lenient: self.lenient,
+            strings: self.strings.clone(),
+            game: self.game,
+            limits: self.limits,
+            encoding: self.encoding,
+            decoders: self.decoders.clone()
         }
-        let mut decoder = ZlibDecoder::new(&v[..]);
-        let mut decompressed_data = Vec::new();
-        decoder.read_to_end(&mut decompressed_data)?;
-        Ok(decompressed_data)
     }
 
-    pub fn GRUP(&mut self, header: &RecordHeader) -> Result<()> {
+    fn apply(&mut self, options: Options) {
+        let Options { localised, lenient, strings, game, limits, encoding, decoders } = options;
+        self.localised = localised;
+        self.lenient = lenient;
+        self.strings = strings;
+        self.game = game;
+        self.limits = limits;
+        self.encoding = encoding;
+        self.decoders = decoders;
+    }
+
+    /// Payload size of a field, given by a preceding `XXXX` field for fields
+    /// larger than 64 KiB.
+    fn field_size(&self, header: &FieldHeader) -> usize {
+        self.oversized.map_or(header.size as usize, |size| size as usize)
+    }
+
+    /// Read a field without decoding it, for `parse_fields` callbacks which
+    /// leave some fields as they are.
+    pub fn raw_field(&mut self, header: &FieldHeader) -> Result<Value> {
+        Ok(Value::Unknown(self.read_bytes(self.field_size(header))?))
+    }
+
+
+    pub fn GRUP(&mut self, header: &RecordHeader, visitor: &mut dyn Visitor) -> Result<()> {
         let RecordHeader { size, type_id, flags, .. } = *header;
 
 //        if self.depth() >= 3 {
@@ -77,1907 +204,434 @@ impl<R> ESMParser<R> where R: std::io::Read + std::io::Seek {
 
         if type_id == b"GRUP" {
             let GRUP: GroupHeader = unsafe { std::mem::transmute(*header) };
-            indentln!(self, "{:?}", GRUP);
+            visitor.enter_group(&GRUP)?;
+            self.parse_records(ESMParser::GRUP, size as u64, visitor)?;
+            return visitor.leave_group(&GRUP)
         } else {
-            if type_id != b"REFR" {
-                indentln!(self, "{:?}", header);
-            }
+            visitor.record(header)?;
 
             if (flags & 0x00040000) != 0 {
-                let _uncompressed_size: u32 = self.read()?;
-                let decompressed = &self.deflate(size as usize - 4)?;
+                let offset = self.reader().stream_position()? - self.layout().header_size() as u64;
+                let uncompressed_size: u32 = self.read()?;
+                let compressed_size = size.checked_sub(4).ok_or(Error::invalid("compressed record without a size"))? as u64;
+                if compressed_size > self.limits.compressed {
+                    return Err(Error::limit("compressed record", compressed_size, self.limits.compressed))
+                }
+                let compressed = self.read_bytes(compressed_size as usize)?;
+                visitor.compressed(&compressed)?;
+                let decompressed = &inflate(&compressed, uncompressed_size as u64, self.limits.inflated)?;
                 let reader = std::io::Cursor::new(decompressed);
                 let mut parser = ESMParser::new(reader);
                 *parser.inner_depth() = self.depth();
-                parser.localised = self.localised;
+                parser.apply(self.options());
+                parser.path = self.path.clone();
+                parser.starts = self.starts.clone();
                 parser.push();
-                // this block is for the first compressed record, NPC_
-                parser.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, decompressed.len() as u32)?;
+                let mut inflated = *header; // same record, sized to the inflated fields
+                inflated.size = u32::try_from(decompressed.len()).map_err(|_| Error::invalid("record larger than 4 GiB"))?;
+                let res = parser.parse_schema(self.layout().schema(), &inflated, visitor);
+                for mut diagnostic in parser.diagnostics.drain(..) {
+                    diagnostic.context_mut().offset = Some(offset); // position in the file, not the inflated buffer
+                    self.diagnostics.push(diagnostic);
+                }
+                res.map_err(|mut e| {
+                    e.context_mut().offset = Some(offset);
+                    e
+                })?;
                 parser.pop();
-                return Ok(())
+                return visitor.leave_record(header)
             }
         }
 
-        match &type_id.0 {
-            b"GLOB" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FNAM" => {
-                            let FNAM: u8 = parser.read()?;
-                            println!("{:?}", FNAM);
-                        },
-                        b"FLTV" => {
-                            let FLTV: f32 = parser.read()?;
-                            println!("{:?}", FLTV);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"FACT" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"XNAM" => {
-                            let XNAM: XNAM = parser.read()?;
-                            println!("{:?}", XNAM);
-                        },
-                        b"DATA" => {
-                            let DATA: u32 = parser.read()?;
-                            println!("{:#010x}", DATA);
-                        },
-                        b"RNAM" => {
-                            let RNAM: u32 = parser.read()?;
-                            println!("{:#010x}", RNAM);
-                        },
-                        b"MNAM" => {
-                            let MNAM = parser.read_lstring(header.size)?;
-                            println!("{:?}", MNAM);
-                        },
-                        b"FNAM" => {
-                            let FNAM = parser.read_lstring(header.size)?;
-                            println!("{:?}", FNAM);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"TXST" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        tx if tx >= b"TX00" && tx <= b"TX07" => {
-                            let TX = parser.read_zstring(header.size)?;
-                            println!("{:?}", TX);
-                        },
-                        b"DNAM" => {
-                            let DNAM: u16 = parser.read()?;
-                            println!("{:?}", DNAM);
-                        },
-                        /*b"DODT" => {
-                            let DODT: DODT = parser.read()?;
-                            println!("{:?}", DODT);
-                        },*/
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!(" Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"CLAS" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, " {:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"DESC" => {
-                            let DESC = parser.read_lstring(header.size)?;
-                            println!("{:?}", DESC);
-                        },
-                        /*b"ICON" => {
-                            let ICON = parser.read_zstring(header.size)?;
-                            println!("{:?}", ICON);
-                        },*/
-                        b"DATA" => {
-                            let CLAS: CLAS = parser.read()?;
-                            println!("{:?}", CLAS);
-                        },
-                        b"ATTR" => {
-                            let ATTR: ATTR = parser.read()?;
-                            println!("{:?}", ATTR);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"SOUN" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"FNAM" => {
-                            let FNAM = parser.read_zstring(header.size)?;
-                            println!("{:?}", FNAM);
-                        },
-                        b"SNDD" => {
-                            let SNDD: SNDD = parser.read()?;
-                            println!("{:?}", SNDD);
-                        },
-                        b"SDSC" => {
-                            let SDSC: formid_t = parser.read()?;
-                            println!("{:?}", SDSC);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"ASPC" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"SNAM" => {
-                            let SNAM: formid_t = parser.read()?;
-                            println!("{:?}", SNAM);
-                        },
-                        b"RDAT" => {
-                            let RDAT: formid_t = parser.read()?;
-                            println!("{:?}", RDAT);
-                        },
-                        b"BNAM" => {
-                            let BNAM: formid_t = parser.read()?;
-                            println!("{:?}", BNAM);
-                        },
-                        b"ANAM" => {
-                            let ANAM: [u8;4] = parser.read()?;
-                            println!("ANAM {{ unknown: {:?} }}", ANAM);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"MGEF" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        //b"VMAD" => {},
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"DESC" => {
-                            let DESC = parser.read_lstring(header.size)?;
-                            println!("{:?}", DESC);
-                        },
-                        /*b"MDOB" => {
-                            let MDOB: formid_t = parser.read()?;
-                            println!("{:?}", MDOB);
-                        },
-                        b"KSIZ" => {
-                            let KSIZ: u32 = parser.read()?;
-                            println!("{:?}", KSIZ);
-                        },
-                        b"KWDA" => {},*/
-                        b"DATA" => {
-                            let MGEF: MGEF = parser.read()?;
-                            println!("{:?}", MGEF);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"ENCH" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"ENIT" => {
-                            let ENIT: ENIT = parser.read()?;
-                            println!("{:?}", ENIT);
-                        },
-                        b"EFID" => {
-                            let EFID: formid_t = parser.read()?;
-                            println!("{:?}", EFID);
-                        },
-                        b"EFIT" => {
-                            let EFIT: EFIT = parser.read()?;
-                            println!("{:?}", EFIT);
-                        },
-                        /*b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },*/
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"SCPT" => {
-                let SCPT = self.read_lstring(size as u16)?;
-                println!("{:?}", SCPT);
-            },
-            b"SPEL" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"SPIT" => {
-                            let SPIT: SPIT = parser.read()?;
-                            println!("{:?}", SPIT);
-                        },
-                        b"EFID" => {
-                            let EFID: formid_t = parser.read()?;
-                            println!("{:?}", EFID);
-                        },
-                        b"EFIT" => {
-                            let EFIT: EFIT = parser.read()?;
-                            println!("{:?}", EFIT);
-                        },
-                        b"CTDA" => {
-                            let CTDA: CTDA = parser.read()?;
-                            println!("{:?}", CTDA);
-                        },
-                        /*b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },*/
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"ACTI" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"SCRI" => {
-                            let SCRI: formid_t = parser.read()?;
-                            println!("{:?}", SCRI);
-                        },
-                        b"VNAM" => {
-                            let VNAM: formid_t = parser.read()?;
-                            println!("{:?}", VNAM);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"SNAM" => {
-                            let SNAM: formid_t = parser.read()?;
-                            println!("{:?}", SNAM);
-                        },
-                        b"DEST" => {
-                            let DEST: [u8;8] = parser.read()?;
-                            println!("DEST {{ unknown: {:?} }}", DEST);
-                        },
-                        b"DSTD" => {
-                            let DSTD: DSTD = parser.read()?;
-                            println!("{:?}", DSTD);
-                        },
-                        b"DSTF" => {
-                            println!();
-                        },
-                        b"DMDL" => {
-                            let DMDL = parser.read_zstring(header.size)?;
-                            println!("{:?}", DMDL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"TERM" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"DESC" => {
-                            let DESC = parser.read_lstring(header.size)?;
-                            println!("{:?}", DESC);
-                        },
-                        b"CTDA" => {
-                            let CTDA: CTDA = parser.read()?;
-                            println!("{:?}", CTDA);
-                        },
-                        b"RNAM" => {
-                            let RNAM = parser.read_zstring(header.size)?;
-                            println!("{:?}", RNAM);
-                        },
-                        b"ITXT" => {
-                            let ITXT = parser.read_zstring(header.size)?;
-                            println!("{:?}", ITXT);
-                        },
-                        b"SNAM" => {
-                            let SNAM: formid_t = parser.read()?;
-                            println!("{:?}", SNAM);
-                        },
-                        b"SCHR" => {
-                            let SCHR: SCHR = parser.read()?;
-                            println!("{:?}", SCHR);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"CONT" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"SCRI" => {
-                            let SCRI: formid_t = parser.read()?;
-                            println!("{:?}", SCRI);
-                        },
-                        b"DATA" => {
-                            let DATA: [u8;5] = parser.read()?;
-                            println!("DATA {{ unknown: {:?} }}", DATA);
-                        },
-                        b"CNTO" => {
-                            let CNTO: CNTO = parser.read()?;
-                            println!("{:?}", CNTO);
-                        },
-                        b"COED" => {
-                            let COED: COED = parser.read()?;
-                            println!("{:?}", COED);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"LIGH" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"SCRI" => {
-                            let SCRI: formid_t = parser.read()?;
-                            println!("{:?}", SCRI);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"MISC" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"ICON" => {
-                            let ICON = parser.read_zstring(header.size)?;
-                            println!("{:?}", ICON);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"STAT" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"MSTT" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"DATA" => {
-                            let DATA: u8 = parser.read()?;
-                            println!("{:?}", DATA);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"PWAT" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"FURN" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"MNAM" => {
-                            let MNAM: u32 = parser.read()?;
-                            println!("{:?}", MNAM);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"WEAP" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"MOD2" => {
-                            let MOD2 = parser.read_zstring(header.size)?;
-                            println!("{:?}", MOD2);
-                        },
-                        b"MOD3" => {
-                            let MOD3 = parser.read_zstring(header.size)?;
-                            println!("{:?}", MOD3);
-                        },
-                        b"MOD4" => {
-                            let MOD4 = parser.read_zstring(header.size)?;
-                            println!("{:?}", MOD4);
-                        },
-                        b"ICON" => {
-                            let ICON = parser.read_zstring(header.size)?;
-                            println!("{:?}", ICON);
-                        },
-                        b"CRDT" => {
-                            let CRDT: CRDT = parser.read()?;
-                            println!("{:?}", CRDT);
-                        },
-                        b"EITM" => {
-                            let EITM: formid_t = parser.read()?;
-                            println!("{:?}", EITM);
-                        },
-                        b"ETYP" => {
-                            let ETYP: u32 = parser.read()?;
-                            println!("{:?}", ETYP);
-                        },
-                        b"DATA" => {
-                            let DATA: DATA = parser.read()?;
-                            println!("{:?}", DATA);
-                        },
-                        b"REPL" => {
-                            let REPL: formid_t = parser.read()?;
-                            println!("{:?}", REPL);
-                        },
-                        b"SCRI" => {
-                            let SCRI: formid_t = parser.read()?;
-                            println!("{:?}", SCRI);
-                        },
-                        b"NAM0" => {
-                            let NAM0: formid_t = parser.read()?;
-                            println!("{:?}", NAM0);
-                        },
-                        b"NAM6" => {
-                            let NAM6: formid_t = parser.read()?;
-                            println!("{:?}", NAM6);
-                        },
-                        b"NAM8" => {
-                            let NAM8: formid_t = parser.read()?;
-                            println!("{:?}", NAM8);
-                        },
-                        b"NAM9" => {
-                            let NAM9: formid_t = parser.read()?;
-                            println!("{:?}", NAM9);
-                        },
-                        b"DNAM" => {
-                            let DNAM: DNAM = parser.read()?;
-                            println!("{:?}", DNAM);
-                        },
-                        b"INAM" => {
-                            let INAM: formid_t = parser.read()?;
-                            println!("{:?}", INAM);
-                        },
-                        b"NNAM" => {
-                            let NNAM = parser.read_zstring(header.size)?;
-                            println!("{:?}", NNAM);
-                        },
-                        b"SNAM" => {
-                            let SNAM: formid_t = parser.read()?;
-                            println!("{:?}", SNAM);
-                        },
-                        b"TNAM" => {
-                            let TNAM: formid_t = parser.read()?;
-                            println!("{:?}", TNAM);
-                        },
-                        b"UNAM" => {
-                            let UNAM: formid_t = parser.read()?;
-                            println!("{:?}", UNAM);
-                        },
-                        b"VNAM" => {
-                            let VNAM: u32 = parser.read()?;
-                            println!("{:?}", VNAM);
-                        },
-                        b"WNAM" => {
-                            let WNAM: formid_t = parser.read()?;
-                            println!("{:?}", WNAM);
-                        },
-                        b"XNAM" => {
-                            let XNAM: formid_t = parser.read()?;
-                            println!("{:?}", XNAM);
-                        },
-                        b"YNAM" => {
-                            let YNAM: formid_t = parser.read()?;
-                            println!("{:?}", YNAM);
-                        },
-                        b"ZNAM" => {
-                            let ZNAM: formid_t = parser.read()?;
-                            println!("{:?}", ZNAM);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"AMMO" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"ICON" => {
-                            let ICON = parser.read_zstring(header.size)?;
-                            println!("{:?}", ICON);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"CREA" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"LVLC" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"ALCH" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"ICON" => {
-                            let ICON = parser.read_zstring(header.size)?;
-                            println!("{:?}", ICON);
-                        },
-                        b"EFID" => {
-                            let EFID: formid_t = parser.read()?;
-                            println!("{:?}", EFID);
-                        },
-                        b"EFIT" => {
-                            let EFIT: EFIT = parser.read()?;
-                            println!("{:?}", EFIT);
-                        },
-                        b"CTDA" => {
-                            let CTDA: CTDA = parser.read()?;
-                            println!("{:?}", CTDA);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"NOTE" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"ICON" => {
-                            let ICON = parser.read_zstring(header.size)?;
-                            println!("{:?}", ICON);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"PROJ" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"LVLI" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"REGN" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"NAVI" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"DIAL" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"QUST" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"IDLE" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"PACK" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"CSTY" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"ANIO" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"WATR" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"EFSH" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"ICON" => {
-                            let ICON = parser.read_zstring(header.size)?;
-                            println!("{:?}", ICON);
-                        },
-                        b"ICO2" => {
-                            let ICO2 = parser.read_zstring(header.size)?;
-                            println!("{:?}", ICO2);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"EXPL" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"DEBR" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"IMGS" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"FLST" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"PERK" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"DESC" => {
-                            let DESC = parser.read_lstring(header.size)?;
-                            println!("{:?}", DESC);
-                        },
-                        b"ICON" => {
-                            let ICON = parser.read_zstring(header.size)?;
-                            println!("{:?}", ICON);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"BPTD" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"ADDN" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"CAMS" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"CPTH" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"CTDA" => {
-                            let CTDA: CTDA = parser.read()?;
-                            println!("{:?}", CTDA);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"VTYP" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"IPCT" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"IPDS" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"ARMA" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"MOD3" => {
-                            let MOD3 = parser.read_zstring(header.size)?;
-                            println!("{:?}", MOD3);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"ECZN" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"MESG" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"DESC" => {
-                            let DESC = parser.read_lstring(header.size)?;
-                            println!("{:?}", DESC);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"RGDL" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"NPC_" => { self.skip(size as u64)?; },
-            b"WRLD" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"CNAM" => {
-                            let CNAM: formid_t = parser.read()?;
-                            println!("{:?}", CNAM);
-                        },
-                        b"XXXX" => {
-                            let XXXX_size: u32 = parser.read()?;
-                            println!();
-                            let next: FieldHeader = parser.read()?;
-                            indentln!(parser, "{:?}", next);
-                            parser.skip(XXXX_size as u64)?;
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"TACT" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"VNAM" => {
-                            let VNAM: formid_t = parser.read()?;
-                            println!("{:?}", VNAM);
-                        },
-                        b"SCRI" => {
-                            let SCRI: formid_t = parser.read()?;
-                            println!("{:?}", SCRI);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"ARMO" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"EITM" => {
-                            let EITM: formid_t = parser.read()?;
-                            println!("{:?}", EITM);
-                        },
-                        b"ICON" => {
-                            let ICON = parser.read_zstring(header.size)?;
-                            println!("{:?}", ICON);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        b"MODS" => {
-                            let MODS = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODS);
-                        },
-                        b"MOD2" => {
-                            let MOD2 = parser.read_zstring(header.size)?;
-                            println!("{:?}", MOD2);
-                        },
-                        b"MOD3" => {
-                            let MOD3 = parser.read_zstring(header.size)?;
-                            println!("{:?}", MOD3);
-                        },
-                        b"MO2S" => {
-                            let MO2S = parser.read_zstring(header.size)?;
-                            println!("{:?}", MO2S);
-                        },
-                        b"MO3S" => {
-                            let MO3S = parser.read_zstring(header.size)?;
-                            println!("{:?}", MO3S);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"DOOR" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"SCRI" => {
-                            let SCRI: formid_t = parser.read()?;
-                            println!("{:?}", SCRI);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"SCOL" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        b"MODL" => {
-                            let MODL = parser.read_zstring(header.size)?;
-                            println!("{:?}", MODL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"IDLM" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"OBND" => {
-                            let OBND: OBND = parser.read()?;
-                            println!("{:?}", OBND);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"IMAD" => { self.skip(size as u64)?; },
-            b"GRUP" => {
-                self.parse_records(ESMParser::GRUP, header.size as u64)?;
-                return Ok(())
-            },
-            b"CELL" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        b"FULL" => {
-                            let FULL = parser.read_lstring(header.size)?;
-                            println!("{:?}", FULL);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"ACRE" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"EDID" => {
-                            let EDID = parser.read_zstring(header.size)?;
-                            println!("{:?}", EDID);
-                        },
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"NAVM" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"ACHR" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"INFO" => {
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        _ => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unknown field '{}'", header.type_id);
-                        }
-                    }
-                    Ok(())
-                }, size)?;
-            },
-            b"REFR" => { self.skip(size as u64)?; },
-            _ => {
-                self.skip(size as u64)?;
-                println!("Unknown record '{}'", type_id);
-            },
-        }
-        Ok(())
+        self.parse_schema(self.layout().schema(), header, visitor)?;
+        visitor.leave_record(header)
     }
 
-    pub fn TES4(&mut self, header: &RecordHeader) -> Result<()> {
+    pub fn TES4(&mut self, header: &RecordHeader, visitor: &mut dyn Visitor) -> Result<()> {
         let RecordHeader { type_id, size, .. } = *header;
         match &type_id.0 {
             b"TES4" => {
-                println!("{:?} ", header);
+                visitor.record(header)?;
 
                 let flags = header.flags;
                 if (flags & 0x00000001) != 0 {} // Master (ESM) file
                 self.localised = (flags & 0x00000080) != 0;
                 if (flags & 0x00000200) != 0 {} // Light Master (ESL) File
 
-                self.parse_fields(|parser, header| {
-                    indent!(parser, "{:?} ", header);
-                    match &header.type_id.0 {
-                        b"HEDR" => {
-                            let HEDR: HEDR = parser.read()?;
-                            println!("{:?}", HEDR);
-                        },
-                        b"CNAM" => {
-                            let CNAM = parser.read_zstring(header.size)?;
-                            println!("{:?}", CNAM);
-                        },
-                        b"MAST" => {
-                            let MAST = parser.read_zstring(header.size)?;
-                            println!("{:?}", MAST);
-                        },
-                        b"DATA" => {
-                            let DATA: u64 = parser.read()?;
-                            println!("{:?}", DATA);
-                        },
-                        b"ONAM" => {
-                            parser.skip(header.size as u64)?;
-                            println!("Unimplemented");
-                        },
-                        _ => { println!("Unknown typeid '{}'", header.type_id) }
-                    }
-                    Ok(())
-                }, size)?;
+                self.parse_schema(&HEADER, header, visitor)?;
+                visitor.leave_record(header)
             },
             b"GRUP" => {
                 let GRUP: GroupHeader = unsafe { std::mem::transmute(*header) };
-                println!("{:?}", GRUP);
-                self.parse_records(ESMParser::GRUP, size as u64)?;
-                return Ok(())
+                visitor.enter_group(&GRUP)?;
+                self.parse_records(ESMParser::GRUP, size as u64, visitor)?;
+                visitor.leave_group(&GRUP)
             },
-            _ => { println!("Unknown record '{}'", type_id)}
+            _ => {
+                visitor.record(header)?;
+                self.parse_fields(ESMParser::raw_field, size, visitor)?;
+                visitor.leave_record(header)
+            }
         }
-        Ok(())
     }
 
-    pub fn parse_fields(&mut self, f: FieldParser<Self>, total_size: u32) -> Result<()> {
+    /// Record the location of every record without decoding any fields.
+    fn INDEX(&mut self, header: &RecordHeader, visitor: &mut dyn Visitor) -> Result<()> {
+        let RecordHeader { type_id, size, flags, id, .. } = *header;
+        if type_id == b"GRUP" {
+            return self.parse_records(ESMParser::INDEX, size as u64, visitor)
+        }
+        if type_id == b"TES4" {
+            self.localised = (flags & 0x00000080) != 0;
+        } else {
+            let offset = self.reader().stream_position()? - self.layout().header_size() as u64;
+            let compressed = (flags & 0x00040000) != 0;
+            self.index.insert(IndexEntry { id: FormId(id), type_id: type_id.0, offset, compressed });
+        }
+        Ok(self.skip(size as u64)?)
+    }
+
+    /// Parse the fields of a record with `f`, which may keep state between
+    /// fields, and pass their values to the visitor.
+    pub fn parse_fields<F>(&mut self, mut f: F, total_size: u32, visitor: &mut dyn Visitor) -> Result<()>
+    where F: FnMut(&mut Self, &FieldHeader) -> Result<Value> {
         if total_size == 0 { return Ok(()) }
         let loop_end = self.reader().stream_position()? + total_size as u64;
         self.push();
         match loop {
-            let header: FieldHeader = self.read()?;
+            let field_start = self.reader().stream_position()?;
+            let mut header: FieldHeader = self.read()?;
+            self.oversized = None;
+            if header.type_id == b"XXXX" { // size of a field larger than 64 KiB
+                if header.size != 4 { return Err(self.locate(Error::invalid("XXXX field without a 4 byte size"))) }
+                self.oversized = Some(self.read()?);
+                header = self.read()?;
+            }
             let start = self.reader().stream_position()?;
-            let size = header.size as u64;
-            f(self, &header)?; // parse the contents
+            let size = self.field_size(&header) as u64;
             let end = start + size;
-            let pos = self.reader().stream_position()?;
+            if end > loop_end { // checked before the untrusted size is allocated
+                break self.within(Segment::field(&header), field_start, |_| {
+                    Err(Error::invalid("field extends past the end of its record"))
+                })
+            }
+            let pos = self.within(Segment::field(&header), field_start, |parser| {
+                let value = f(parser, &header)?; // parse the contents
+                let pos = parser.reader().stream_position()?;
+                if pos != end && pos != loop_end { // function made a mistake
+                    return Err(Error::size(size, pos.saturating_sub(start)))
+                }
+                visitor.field(&header, value)?;
+                Ok(pos)
+            })?;
             if pos == loop_end { break Ok(()) } // function consumed chunk
-            else if pos != end { return Err(chunk_parser::Error::ParseError) } // function made a mistake
         } {
             res => { self.pop(); res }
         }
     }
 
-    fn parse_records(&mut self, f: RecordParser<Self>, total_size: u64) -> Result<()> {
+    /// Parse the records and groups of a group with `f`, which may keep state
+    /// between calls. Callbacks given a `GRUP` header descend by calling this
+    /// with `header.size`, which no longer counts the group header.
+    pub fn parse_records<F>(&mut self, mut f: F, total_size: u64, visitor: &mut dyn Visitor) -> Result<()>
+    where F: FnMut(&mut Self, &RecordHeader, &mut dyn Visitor) -> Result<()> {
         if total_size == 0 { return Ok(()) }
         let loop_end = self.reader().stream_position()? + total_size as u64;
         self.push();
         match loop {
             let start = self.reader().stream_position()?;
-            let mut header: RecordHeader = self.read()?;
-            let mut size = header.size as u64;
-            if header.type_id != b"GRUP" { size += 24; }
-            else { header.size -= 24; }
-            f(self, &header)?; // parse the contents
-            let end = start + size;
-            let pos = self.reader().stream_position()?;
-            if pos == loop_end { break Ok(()) } // function consumed chunk
-            else if pos != end { return Err(chunk_parser::Error::ParseError) } // function made a mistake
+            let header = self.read_header()?;
+            match self.parse_entry(&mut f, start, header, loop_end, visitor) {
+                Ok(pos) if pos == loop_end => break Ok(()), // function consumed chunk
+                Ok(_) => (),
+                Err(error) => break Err(error)
+            }
         } {
             res => { self.pop(); res }
         }
     }
 
-    pub fn parse_top_level(&mut self, f: RecordParser<Self>) -> Result<()> {
+    /// Parse a record or group whose header was read from `start` with `f`,
+    /// returning the position after it.
+    fn parse_entry<F>(
+        &mut self, f: &mut F, start: u64, mut header: RecordHeader, loop_end: u64, visitor: &mut dyn Visitor
+    ) -> Result<u64>
+    where F: FnMut(&mut Self, &RecordHeader, &mut dyn Visitor) -> Result<()> {
+        let mut size = header.size as u64;
+        let header_size = self.layout().header_size() as u32;
+        let segment = if header.type_id != b"GRUP" {
+            size += header_size as u64;
+            Segment::record(&header)
+        } else {
+            header.size = header.size.checked_sub(header_size).ok_or(Error::invalid("group smaller than its header"))?;
+            Segment::group(&unsafe { std::mem::transmute::<RecordHeader, GroupHeader>(header) })
+        };
+        let end = start + size;
+        if end > loop_end { // checked before a decoder reads or allocates the untrusted size
+            return self.within(segment, start, |_| Err(Error::invalid("entry extends past the end of its group")))
+        }
+        let res = self.within(segment, start, |parser| {
+            f(parser, &header, visitor)?; // parse the contents
+            let pos = parser.reader().stream_position()?;
+            if pos != end && pos != loop_end { // function made a mistake
+                return Err(Error::size(size, pos.saturating_sub(start)))
+            }
+            Ok(pos)
+        });
+        match res {
+            Err(error) if self.lenient && header.type_id != b"GRUP" && end <= loop_end => {
+                self.recover(error, &header, end, visitor)
+            },
+            res => res
+        }
+    }
+
+    /// Run `f` with a segment starting at `start` appended to the error path.
+    fn within<T>(&mut self, segment: Segment, start: u64, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.path.0.push(segment);
+        self.starts.push(start);
+        let res = f(self).map_err(|e| self.locate(e));
+        self.path.0.pop();
+        self.starts.pop();
+        res
+    }
+
+    /// Keep a record error as a diagnostic and resume at the record's declared end.
+    fn recover(&mut self, error: Error, header: &RecordHeader, end: u64, visitor: &mut dyn Visitor) -> Result<u64> {
+        self.diagnostics.push(error);
+        self.reader().seek(std::io::SeekFrom::Start(end))?;
+        visitor.leave_record(header)?;
+        Ok(end)
+    }
+
+    /// Fill in where an error occurred, unless a nested call already did. The
+    /// offset is the start of the innermost segment, or the stream position
+    /// outside of any.
+    fn locate(&mut self, mut error: Error) -> Error {
+        let position = match self.starts.last() {
+            Some(&start) => Some(start),
+            None => self.reader().stream_position().ok()
+        };
+        let context = error.context_mut();
+        if context.offset.is_none() {
+            context.offset = position;
+            context.path = self.path.clone();
+        }
+        error
+    }
+
+    /// Parse every top level record and group with `f`, which may keep state
+    /// between calls.
+    pub fn parse_top_level<F>(&mut self, f: F, visitor: &mut dyn Visitor) -> Result<()>
+    where F: FnMut(&mut Self, &RecordHeader, &mut dyn Visitor) -> Result<()> {
+        if self.game.is_none() { self.detect_game()?; }
+        self.diagnostics.clear();
+        if self.forward { return self.parse_forward(f, visitor) }
         let total_size = self.reader().seek(std::io::SeekFrom::End(0))?;
         self.reader().seek(std::io::SeekFrom::Start(0))?;
 //        self.pop();
-        self.parse_records(f, total_size)?;
+        self.parse_records(f, total_size, visitor)?;
 //        self.push();
         Ok(())
     }
+
+    /// Parse top level records until the end of a stream of unknown size,
+    /// reading only forward.
+    fn parse_forward<F>(&mut self, mut f: F, visitor: &mut dyn Visitor) -> Result<()>
+    where F: FnMut(&mut Self, &RecordHeader, &mut dyn Visitor) -> Result<()> {
+        self.reader().seek(std::io::SeekFrom::Start(0))?; // fails once the stream was consumed
+        self.push();
+        let res = loop {
+            let start = self.reader().stream_position()?;
+            let header = match self.next_header() {
+                Ok(Some(header)) => header,
+                Ok(None) => break Ok(()), // clean end between records
+                Err(error) => break Err(error)
+            };
+            if let Err(error) = self.parse_entry(&mut f, start, header, u64::MAX, visitor) { break Err(error) }
+        };
+        self.pop();
+        res
+    }
+
+    /// Replace the dump output, stdout by default.
+    pub fn set_output(&mut self, output: impl std::io::Write + Send + 'static) {
+        self.output = Sink::new(output);
+    }
+
+    /// Decode strings with the given codepage instead of the default for the
+    /// language of the string tables.
+    pub fn set_encoding(&mut self, encoding: &'static Encoding) {
+        self.encoding = Some(encoding);
+    }
+
+    /// Codepage used for plugin strings.
+    pub fn encoding(&self) -> &'static Encoding {
+        self.encoding.unwrap_or_else(|| default_encoding(self.layout(), self.strings.language()))
+    }
+
+    /// Replace the size limits for compressed records.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Select the game the plugin was made for, overriding detection.
+    pub fn set_game(&mut self, game: GameKind) {
+        self.game = Some(game);
+        self.sync_decoders();
+    }
+
+    /// Game selected or detected, `None` until the first parse.
+    pub fn game(&self) -> Option<GameKind> { self.game }
+
+    /// Detect the game from the first record and use it from now on.
+    pub fn detect_game(&mut self) -> Result<GameKind> {
+        let position = self.reader().stream_position()?;
+        self.reader().seek(std::io::SeekFrom::Start(0))?;
+        let mut data = Vec::with_capacity(GameKind::DETECT_SIZE);
+        self.reader().take(GameKind::DETECT_SIZE as u64).read_to_end(&mut data)?;
+        self.reader().seek(std::io::SeekFrom::Start(position))?;
+        let game = GameKind::detect(&data)?;
+        self.game = Some(game);
+        self.sync_decoders();
+        Ok(game)
+    }
+
+    /// Game used for the header layout and field decoders.
+    fn layout(&self) -> GameKind { self.game.unwrap_or_default() }
+
+    /// Decode `field` of `record` with `decoder` instead of the built-in
+    /// decoder, see `Decoders`.
+    pub fn register_decoder<F>(&mut self, record: &[u8;4], field: &[u8;4], decoder: F)
+    where F: Fn(&FieldContext, &[u8]) -> Result<Value> + Send + Sync + 'static {
+        Arc::make_mut(&mut self.decoders).register(record, field, decoder);
+    }
+
+    /// Replace all registered decoders, the defaults follow the parser's game.
+    pub fn set_decoders(&mut self, decoders: Decoders) {
+        self.decoders = Arc::new(decoders);
+        self.sync_decoders();
+    }
+
+    /// Keep the default decoders at the game being parsed.
+    fn sync_decoders(&mut self) {
+        let game = self.layout();
+        if self.decoders.game() != game { Arc::make_mut(&mut self.decoders).set_game(game); }
+    }
+
+    pub fn decoders(&self) -> &Decoders { &self.decoders }
+
+    /// Use the string tables of a localised plugin, see `Strings::load`.
+    /// Without them localised strings decode as missing.
+    pub fn set_strings(&mut self, strings: Strings) {
+        self.strings = Arc::new(strings);
+    }
+
+    /// Load and use the string tables of the plugin at `plugin`, decoded with
+    /// the encoding set or else the default of the game and language.
+    pub fn load_strings(&mut self, plugin: impl AsRef<std::path::Path>, language: &str) -> Result<()> {
+        if self.game.is_none() { self.detect_game()?; }
+        let encoding = self.encoding.unwrap_or_else(|| default_encoding(self.layout(), Some(language)));
+        self.set_strings(Strings::load(plugin, language, encoding)?);
+        Ok(())
+    }
+
+    /// Skip malformed records instead of failing, keeping their errors as
+    /// diagnostics. Records which failed part way keep the fields decoded
+    /// before the error.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// Problems found by the last parse, strings which did not decode and
+    /// records skipped in lenient mode.
+    pub fn diagnostics(&self) -> &[Error] { &self.diagnostics }
+
+    /// Dump the whole file as text to the output.
+    pub fn dump(&mut self) -> Result<()> {
+        let mut output = std::mem::take(&mut self.output);
+        let res = self.parse_top_level(ESMParser::TES4, &mut Dump::new(&mut output));
+        self.output = output;
+        res
+    }
+
+    /// Scan the file for record locations, keeping the diagnostics of the
+    /// last parse.
+    pub fn build_index(&mut self) -> Result<&Index> {
+        if self.forward { return Err(Error::invalid("no random access on a forward only stream")) }
+        self.index = Index::default();
+        self.indexed = false;
+        let diagnostics = std::mem::take(&mut self.diagnostics);
+        let res = self.parse_top_level(ESMParser::INDEX, &mut ());
+        self.diagnostics = diagnostics;
+        res?;
+        self.indexed = true;
+        Ok(&self.index)
+    }
+
+    /// Record locations found by `build_index`.
+    pub fn index(&self) -> &Index { &self.index }
+
+    /// Seek to a single record and decode it, building the index on first use.
+    pub fn get(&mut self, id: FormId) -> Result<Option<Record>> {
+        if !self.indexed { self.build_index()?; }
+        let Some(entry) = self.index.get(id).copied() else { return Ok(None) };
+        self.reader().seek(std::io::SeekFrom::Start(entry.offset))?;
+        let header = self.read_header()?;
+        let mut builder = RecordBuilder::default();
+        self.within(Segment::record(&header), entry.offset, |parser| parser.GRUP(&header, &mut builder))?;
+        builder.finish().map(Some)
+    }
+
+    /// Parse the whole file into a `Plugin`.
+    pub fn parse_plugin(&mut self) -> Result<Plugin> {
+        let mut builder = PluginBuilder::default();
+        self.parse_top_level(ESMParser::TES4, &mut builder)?;
+        self.finish_plugin(builder)
+    }
+
+    fn finish_plugin(&self, builder: PluginBuilder) -> Result<Plugin> {
+        let mut plugin = builder.finish()?;
+        plugin.game = self.layout();
+        plugin.encoding = self.encoding();
+        Ok(plugin)
+    }
+}
+
+/// Decompress a Zlib buffer, which must inflate to exactly `declared` bytes.
+pub(crate) fn inflate(data: &[u8], declared: u64, limit: u64) -> Result<Vec<u8>> {
+    if declared > limit { return Err(Error::limit("inflated record", declared, limit)) }
+    let decoder = ZlibDecoder::new(data);
+    let mut decompressed_data = Vec::new(); // grown as it inflates, the declared size is untrusted
+    decoder.take(declared + 1).read_to_end(&mut decompressed_data).map_err(Error::zlib)?; // one more to spot overruns
+    if decompressed_data.len() as u64 != declared {
+        return Err(Error::inflated(declared, decompressed_data.len() as u64))
+    }
+    Ok(decompressed_data)
 }
 
 //------------------------------------------------------------------------------
 
 pub mod prelude {
     pub use chunk_parser::prelude::*;
-    pub use super::ESMParser;
+    #[cfg(feature = "async")]
+    pub use super::asynchronous::*;
+    pub use super::{ESMParser, Limits};
+    pub use super::decoders::*;
+    pub use super::encoding::*;
+    pub use super::error::Error;
+    pub use super::field::*;
+    pub use super::form_id::*;
+    pub use super::game::*;
+    pub use super::index::*;
+    pub use super::load_order::*;
+    pub use super::mapped::*;
+    pub use super::schema::{FieldDef, Kind, RecordDef, Repeat, Schema};
+    pub use super::stream::*;
+    pub use super::model::*;
+    pub use super::strings::*;
+    pub use super::visitor::*;
+    pub use super::writer::*;
 }
 
 //==============================================================================
@@ -1985,11 +639,854 @@ pub mod prelude {
 #[cfg(test)]
 mod tests {
     use super::prelude::*;
+    use esm_bindings::fo3::FieldHeader;
+
+    #[test]
+    fn zeta() -> super::Result<()> {
+        const DATA: &[u8] = include_bytes!("../data/Zeta.esm");
+        let mut esm = ESMParser::cursor(DATA);
+        esm.dump()
+    }
+
+    #[test]
+    fn zeta_index() -> super::Result<()> {
+        const DATA: &[u8] = include_bytes!("../data/Zeta.esm");
+        let plugin = ESMParser::cursor(DATA).parse_plugin()?;
+        let mut esm = ESMParser::cursor(DATA);
+        assert_eq!(esm.build_index()?.len(), plugin.records().count());
+        for record in plugin.records() {
+            let found = esm.get(record.form_id())?.unwrap();
+            assert_eq!(found.fields.len(), record.fields.len());
+        }
+        assert!(esm.get(FormId(0xFFFFFFFF))?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn index_diagnostics() -> super::Result<()> {
+        struct Scans<'a>(std::io::Cursor<&'a [u8]>, usize); // counts the passes over the file
+        impl std::io::Read for Scans<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> { self.0.read(buf) }
+        }
+        impl std::io::Seek for Scans<'_> {
+            fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+                if let std::io::SeekFrom::End(_) = pos { self.1 += 1; }
+                self.0.seek(pos)
+            }
+        }
+        let data = [tes4(0), group(b"GLOB", &[record(b"GLOB", 1, &[field(b"EDID", b"Global")])])].concat();
+        let mut esm = ESMParser::cursor(&data);
+        esm.parse_plugin()?;
+        assert_eq!(esm.diagnostics().len(), 1);
+        assert!(esm.get(FormId(2))?.is_none());
+        assert_eq!(esm.diagnostics().len(), 1);
+
+        let data = tes4(0);
+        let mut esm = ESMParser::new(Scans(std::io::Cursor::new(&data), 0));
+        assert!(esm.get(FormId(1))?.is_none());
+        assert!(esm.get(FormId(1))?.is_none());
+        assert_eq!(esm.reader().1, 1); // an empty index is not rebuilt
+        Ok(())
+    }
+
+    #[test]
+    fn zeta_roundtrip() -> super::Result<()> {
+        const DATA: &[u8] = include_bytes!("../data/Zeta.esm");
+        let plugin = ESMParser::cursor(DATA).parse_plugin()?;
+        let mut output = Vec::new();
+        Writer::new(&mut output).write_plugin(&plugin)?;
+        assert!(output == DATA);
+        Ok(())
+    }
+
+    #[test]
+    fn zeta_recompress() -> super::Result<()> {
+        const DATA: &[u8] = include_bytes!("../data/Zeta.esm");
+        let mut plugin = ESMParser::cursor(DATA).parse_plugin()?;
+        for group in &mut plugin.groups { forget_compressed(group); }
+        let mut output = Vec::new();
+        Writer::new(&mut output).write_plugin(&plugin)?;
+        let reparsed = ESMParser::cursor(&output).parse_plugin()?;
+        let fields = |plugin: &Plugin| plugin.records().map(|record| record.fields.len()).collect::<Vec<_>>();
+        assert_eq!(fields(&reparsed), fields(&plugin));
+        Ok(())
+    }
+
+    fn forget_compressed(group: &mut Group) {
+        for entry in &mut group.entries {
+            match entry {
+                Entry::Group(group) => forget_compressed(group),
+                Entry::Record(record) => record.compressed = None
+            }
+        }
+    }
+
+    fn field(type_id: &[u8;4], data: &[u8]) -> Vec<u8> {
+        [&type_id[..], &(data.len() as u16).to_le_bytes(), data].concat()
+    }
+
+    fn record(type_id: &[u8;4], id: u32, fields: &[Vec<u8>]) -> Vec<u8> {
+        let data = fields.concat();
+        [&type_id[..], &(data.len() as u32).to_le_bytes(), &[0; 4], &id.to_le_bytes(), &[0; 8], &data].concat()
+    }
+
+    fn tes4(flags: u32) -> Vec<u8> {
+        let hedr = [0.94f32.to_le_bytes(), 0u32.to_le_bytes(), 0x800u32.to_le_bytes()].concat();
+        let mut tes4 = record(b"TES4", 0, &[field(b"HEDR", &hedr)]);
+        tes4[8..12].copy_from_slice(&flags.to_le_bytes());
+        tes4
+    }
+
+    fn group(label: &[u8;4], children: &[Vec<u8>]) -> Vec<u8> {
+        let data = children.concat();
+        [&b"GRUP"[..], &(data.len() as u32 + 24).to_le_bytes(), label, &[0; 12], &data].concat()
+    }
+
+    #[test]
+    fn size_mismatch() {
+        let data = [
+            tes4(0),
+            group(b"WEAP", &[record(b"WEAP", 0x00012345, &[field(b"OBND", &[0; 14])])])
+        ].concat();
+        let err = ESMParser::cursor(&data).parse_plugin().unwrap_err();
+        assert!(matches!(err, Error::Size { expected: 14, consumed: 12, .. }));
+        assert_eq!(err.to_string(), "expected 14 bytes, consumed 12 at 0x5a in GRUP WEAP > WEAP 0x00012345 > OBND");
+    }
+
+    #[test]
+    fn schema() -> super::Result<()> {
+        let data = [
+            tes4(0),
+            group(b"TXST", &[record(b"TXST", 1, &[
+                field(b"EDID", b"Rust\0"),
+                field(b"TX00", b"a.dds\0"),
+                field(b"TX01", b"b.dds\0"),
+                field(b"EDID", b"Again\0")
+            ])])
+        ].concat();
+        let mut esm = ESMParser::cursor(&data);
+        let plugin = esm.parse_plugin()?;
+        let fields = &plugin.records().next().unwrap().fields;
+        assert!(fields.iter().all(|field| matches!(field.value, Value::ZString(_))));
+        assert_eq!(esm.diagnostics().len(), 1);
+        assert_eq!(esm.diagnostics()[0].to_string(), "field repeated in record at 0x7d in GRUP TXST > TXST 0x00000001 > EDID");
+
+        let data = [
+            tes4(0),
+            group(b"GLOB", &[record(b"GLOB", 1, &[field(b"FLTV", &[0; 4]), field(b"FNAM", b"f")])]),
+            group(b"FACT", &[record(b"FACT", 2, &[
+                field(b"EDID", b"Faction\0"),
+                field(b"RNAM", &[0; 4]), field(b"MNAM", b"Male\0"),
+                field(b"RNAM", &[1; 4]), field(b"MNAM", b"Male\0")
+            ])])
+        ].concat();
+        let mut esm = ESMParser::cursor(&data);
+        esm.parse_plugin()?;
+        let reasons: Vec<_> = esm.diagnostics().iter().map(|e| e.to_string()).collect();
+        assert_eq!(reasons, ["field out of order in record at 0x64 in GRUP GLOB > GLOB 0x00000001 > FNAM"]);
+        Ok(())
+    }
+
+    #[derive(Debug, PartialEq, EsmField)]
+    struct Layout {
+        health: i32,
+        flags: [u8; 2],
+        weight: Option<f32>,
+        #[esm(since = 15)]
+        count: Option<u16>
+    }
+
+    #[test]
+    fn derived_fields() -> super::Result<()> {
+        let data = [&7i32.to_le_bytes()[..], &[1, 2], &2.5f32.to_le_bytes(), &3u16.to_le_bytes()].concat();
+        let layout = Layout::decode(&data, 15)?;
+        assert_eq!(layout, Layout { health: 7, flags: [1, 2], weight: Some(2.5), count: Some(3) });
+        assert_eq!(layout.encode(), data);
+        assert_eq!(Layout::decode(&data[..6], 15)?, Layout { health: 7, flags: [1, 2], weight: None, count: None });
+        assert!(matches!(Layout::decode(&data, 14), Err(Error::Size { expected: 12, consumed: 10, .. })));
+        assert!(matches!(Layout::decode(&data[..4], 15), Err(Error::Size { expected: 4, consumed: 5, .. })));
+
+        let data = [
+            tes4(0),
+            group(b"CONT", &[record(b"CONT", 1, &[field(b"DATA", &[&[1u8][..], &0.5f32.to_le_bytes()].concat())])])
+        ].concat();
+        let plugin = ESMParser::cursor(&data).parse_plugin()?;
+        let value = &plugin.records().next().unwrap().fields[0].value;
+        assert_eq!(format!("{:?}", value), "ContainerData { flags: 1, weight: 0.5 }");
+        let mut output = Vec::new();
+        Writer::new(&mut output).write_plugin(&plugin)?;
+        assert_eq!(output, data);
+        Ok(())
+    }
+
+    #[test]
+    fn form_ids() -> super::Result<()> {
+        let hedr = [0.94f32.to_le_bytes(), 0u32.to_le_bytes(), 0x800u32.to_le_bytes()].concat();
+        let data = [
+            record(b"TES4", 0, &[
+                field(b"HEDR", &hedr),
+                field(b"MAST", b"Fallout3.esm\0"), field(b"DATA", &[0; 8]),
+                field(b"MAST", b"Anchorage.esm\0"), field(b"DATA", &[0; 8])
+            ]),
+            group(b"LIGH", &[record(b"LIGH", 0x0200_0801, &[field(b"SCRI", &0x0000_ABCDu32.to_le_bytes())])])
+        ].concat();
+        let plugin = ESMParser::cursor(&data).parse_plugin()?;
+        let masters = Masters::of(&plugin, "Mod.esp");
+        let light = plugin.records().next().unwrap();
+        let Some(&Value::FormId(script)) = light.get(b"SCRI") else { panic!("SCRI is not a form ID") };
+        assert_eq!(masters.display(script).to_string(), "Fallout3.esm:0x00ABCD");
+        assert_eq!(masters.display(light.form_id()).to_string(), "Mod.esp:0x000801");
+        assert_eq!(masters.display(FormId::new(3, 1)).to_string(), "<missing master 0x03>:0x000001");
+        assert_eq!(format!("{:?}", light.form_id()), "0x02000801");
+
+        let load_order = ["fallout3.esm", "Mod.esp", "Anchorage.esm"];
+        let global = masters.to_global(light.form_id(), &load_order)?;
+        assert_eq!(global, FormId::new(1, 0x801));
+        assert_eq!(masters.from_global(global, &load_order)?, light.form_id());
+        assert_eq!(masters.to_global(FormId::new(1, 2), &load_order)?, FormId::new(2, 2));
+        assert!(masters.to_global(script, &["Anchorage.esm", "Mod.esp"]).is_err());
+        assert!(Masters::new(Vec::new(), "Other.esp").from_global(global, &load_order).is_err());
+
+        let data = [
+            record(b"TES4", 0, &[
+                field(b"HEDR", &hedr),
+                field(b"MAST", b"Fallout3.esm"), field(b"DATA", &[0; 8]), // no terminator
+                field(b"MAST", b"Anchorage.esm\0"), field(b"DATA", &[0; 8])
+            ]),
+            group(b"LIGH", &[record(b"LIGH", 0x0100_0801, &[])])
+        ].concat();
+        let mut esm = ESMParser::cursor(&data);
+        let plugin = esm.parse_plugin()?;
+        assert_eq!(esm.diagnostics().len(), 1);
+        let masters = Masters::of(&plugin, "Mod.esp");
+        assert_eq!(masters.masters(), ["Fallout3.esm", "Anchorage.esm"]);
+        let light = plugin.records().next().unwrap();
+        assert_eq!(masters.display(light.form_id()).to_string(), "Anchorage.esm:0x000801");
+        Ok(())
+    }
+
+    #[test]
+    fn load_order() -> super::Result<()> {
+        let plugin = |masters: &[&str], records: &[(u32, &str)]| -> super::Result<Plugin> {
+            let hedr = [0.94f32.to_le_bytes(), 0u32.to_le_bytes(), 0x800u32.to_le_bytes()].concat();
+            let mut header = vec![field(b"HEDR", &hedr)];
+            for master in masters {
+                header.push(field(b"MAST", &[master.as_bytes(), &[0]].concat()));
+                header.push(field(b"DATA", &[0; 8]));
+            }
+            let records: Vec<_> = records.iter()
+                .map(|(id, name)| record(b"GLOB", *id, &[field(b"EDID", &[name.as_bytes(), &[0]].concat())]))
+                .collect();
+            ESMParser::cursor(&[record(b"TES4", 0, &header), group(b"GLOB", &records)].concat()).parse_plugin()
+        };
+        let mut load_order = LoadOrder::new();
+        load_order.push("Base.esm", plugin(&[], &[(0x00000001, "One"), (0x00000002, "Two")])?)?;
+        load_order.push("DLC.esm", plugin(&["Base.esm"], &[(0x00000001, "OneDLC"), (0x01000003, "Three")])?)?;
+        let err = load_order.push("Patch.esp", plugin(&["Base.esm", "Missing.esm"], &[])?).unwrap_err();
+        assert_eq!(err.to_string(), "Patch.esp requires Missing.esm, which is not loaded before it");
+        load_order.push("Mod.esp", plugin(&["base.esm", "DLC.esm"], &[(0x00000002, "TwoMod"), (0x01000003, "ThreeMod")])?)?;
+
+        let edid = |id: FormId| load_order.winning_record(id).and_then(|record| record.get(b"EDID")).map(|value| format!("{:?}", value));
+        assert_eq!(edid(FormId::new(0, 1)).as_deref(), Some("\"OneDLC\""));
+        assert_eq!(edid(FormId::new(0, 2)).as_deref(), Some("\"TwoMod\""));
+        assert_eq!(edid(FormId::new(1, 3)).as_deref(), Some("\"ThreeMod\""));
+        assert_eq!(load_order.winner(FormId::new(1, 3)).map(LoadedPlugin::name), Some("Mod.esp"));
+        let positions = |id| load_order.definitions(id).iter().map(|definition| definition.position).collect::<Vec<_>>();
+        assert_eq!(positions(FormId::new(0, 1)), [0, 1]);
+        assert!(load_order.winner(FormId::new(2, 1)).is_none());
+
+        assert_eq!(load_order.to_global(2, FormId::new(1, 3))?, FormId::new(1, 3));
+        assert_eq!(load_order.to_local(2, FormId::new(1, 3))?, FormId::new(1, 3));
+        assert_eq!(load_order.to_local(1, FormId::new(1, 3))?, FormId::new(1, 3));
+        assert!(load_order.to_local(0, FormId::new(1, 3)).is_err());
+        let ids: Vec<_> = load_order.records().map(|(id, position, _)| (id, position)).collect();
+        assert_eq!(ids.len(), 6);
+        assert_eq!(ids[5], (FormId::new(1, 3), 2));
+
+        load_order.push("Twice.esp", plugin(&["Base.esm"], &[(0x00000001, "First"), (0x00000001, "Second")])?)?;
+        let positions = |id| load_order.definitions(id).iter().map(|definition| definition.position).collect::<Vec<_>>();
+        assert_eq!(positions(FormId::new(0, 1)), [0, 1, 3]);
+        let edid = load_order.winning_record(FormId::new(0, 1)).and_then(|record| record.get(b"EDID"));
+        assert_eq!(format!("{:?}", edid.unwrap()), "\"Second\"");
+        Ok(())
+    }
+
+    #[test]
+    fn lenient() -> super::Result<()> {
+        let data = [
+            tes4(0),
+            group(b"WEAP", &[
+                record(b"WEAP", 0x00012345, &[field(b"EDID", b"Broken\0"), field(b"OBND", &[0; 14])]),
+                record(b"WEAP", 0x00012346, &[field(b"EDID", b"Intact\0")])
+            ])
+        ].concat();
+        let mut esm = ESMParser::cursor(&data);
+        esm.set_lenient(true);
+        let plugin = esm.parse_plugin()?;
+        assert_eq!(esm.diagnostics().len(), 1);
+        assert!(matches!(esm.diagnostics()[0], Error::Size { expected: 14, consumed: 12, .. }));
+        let records: Vec<_> = plugin.records().collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].fields.len(), 1);
+        assert_eq!({ records[1].header.id }, 0x00012346);
+        assert!(records[1].get(b"EDID").is_some());
+        Ok(())
+    }
+
+    fn string_table(entries: &[(u32, &[u8])], kind: StringKind) -> Vec<u8> {
+        let mut directory = Vec::new();
+        let mut data = Vec::new();
+        for (id, string) in entries {
+            directory.extend([id.to_le_bytes(), (data.len() as u32).to_le_bytes()].concat());
+            if kind != StringKind::Strings { data.extend((string.len() as u32 + 1).to_le_bytes()); }
+            data.extend([*string, b"\0"].concat());
+        }
+        [&(entries.len() as u32).to_le_bytes()[..], &(data.len() as u32).to_le_bytes(), &directory, &data].concat()
+    }
 
     #[test]
-    fn zeta() -> chunk_parser::Result<()> {
+    fn localised() -> super::Result<()> {
+        let data = [
+            tes4(0x80), // localised
+            group(b"CLAS", &[record(b"CLAS", 0x00012345, &[
+                field(b"FULL", &1u32.to_le_bytes()),
+                field(b"DESC", &2u32.to_le_bytes())
+            ])])
+        ].concat();
+        let mut strings = Strings::default();
+        let table = string_table(&[(1, b"Soldier")], StringKind::Strings);
+        strings.set(StringKind::Strings, StringTable::parse(&table, StringKind::Strings, WINDOWS_1252)?);
+        let table = string_table(&[(1, b"Not a description")], StringKind::DLStrings);
+        strings.set(StringKind::DLStrings, StringTable::parse(&table, StringKind::DLStrings, WINDOWS_1252)?);
+
+        let mut esm = ESMParser::cursor(&data);
+        esm.set_strings(strings);
+        let plugin = esm.parse_plugin()?;
+        let record = plugin.records().next().unwrap();
+        assert_eq!(format!("{:?}", record.get(b"FULL").unwrap()), "\"Soldier\"");
+        assert_eq!(format!("{:?}", record.get(b"DESC").unwrap()), "<missing string 0x00000002>");
+
+        let mut output = Vec::new();
+        Writer::new(&mut output).write_plugin(&plugin)?;
+        assert_eq!(output, data);
+        Ok(())
+    }
+
+    #[test]
+    fn zeta_game() {
+        const DATA: &[u8] = include_bytes!("../data/Zeta.esm");
+        assert_eq!(GameKind::detect(DATA).unwrap(), GameKind::Fallout3);
+        assert!(GameKind::detect(b"TES3").is_err());
+    }
+
+    #[test]
+    fn detect_games() {
+        let header = |form_version: u16, version: f32| -> Vec<u8> {
+            [&b"TES4"[..], &[0; 16], &form_version.to_le_bytes(), &[0; 2], b"HEDR", &[12, 0], &version.to_le_bytes()].concat()
+        };
+        let oblivion = [&b"TES4"[..], &[0; 16], b"HEDR", &[12, 0], &1.0f32.to_le_bytes(), &[0; 4]].concat();
+        let cases = [
+            (oblivion, GameKind::Oblivion),
+            (header(15, 0.94), GameKind::Fallout3),
+            (header(0, 0.94), GameKind::Fallout3),
+            (header(15, 1.34), GameKind::FalloutNV),
+            (header(0, 1.32), GameKind::FalloutNV),
+            (header(43, 0.94), GameKind::SkyrimLE),
+            (header(40, 1.7), GameKind::SkyrimLE),
+            (header(44, 1.71), GameKind::SkyrimSE),
+            (header(0, 1.7), GameKind::SkyrimSE),
+            (header(131, 1.0), GameKind::Fallout4),
+            (header(0, 0.95), GameKind::Fallout4)
+        ];
+        for (data, game) in cases {
+            assert_eq!(data.len(), GameKind::DETECT_SIZE);
+            assert_eq!(GameKind::detect(&data).unwrap(), game, "{:?}", &data[20..]);
+        }
+        assert!(GameKind::detect(&header(44, 1.71)[..33]).is_err());
+    }
+
+    #[test]
+    fn oblivion() -> super::Result<()> {
+        let fields = [field(b"EDID", b"Coin\0"), field(b"DATA", &[1, 0, 0, 0, 0, 0, 0x80, 0x3f])].concat();
+        let misc = [&b"MISC"[..], &(fields.len() as u32).to_le_bytes(), &[0; 12], &fields].concat();
+        let data = [
+            &b"TES4"[..], &18u32.to_le_bytes(), &[0; 12], &field(b"HEDR", &[0; 12]),
+            b"GRUP", &(misc.len() as u32 + 20).to_le_bytes(), b"MISC", &[0; 8], &misc
+        ].concat();
+        let mut esm = ESMParser::cursor(&data);
+        let plugin = esm.parse_plugin()?;
+        assert_eq!(esm.game(), Some(GameKind::Oblivion));
+        let record = plugin.records().next().unwrap();
+        assert_eq!(format!("{:?}", record.get(b"EDID").unwrap()), "\"Coin\"");
+        assert!(matches!(record.get(b"DATA"), Some(Value::Bytes(bytes)) if bytes.len() == 8));
+        assert!(esm.diagnostics().is_empty());
+
+        let mut output = Vec::new();
+        Writer::new(&mut output).write_plugin(&plugin)?;
+        assert_eq!(output, data);
+        Ok(())
+    }
+
+    #[test]
+    fn game_schemas() -> super::Result<()> {
+        let data = [
+            tes4(0),
+            group(b"KYWD", &[record(b"KYWD", 1, &[field(b"EDID", b"Keyword\0"), field(b"CNAM", &[1, 2, 3, 0])])]),
+            group(b"FLST", &[record(b"FLST", 2, &[
+                field(b"LNAM", &1u32.to_le_bytes()), field(b"EDID", b"List\0"), field(b"LNAM", &2u32.to_le_bytes())
+            ])])
+        ].concat();
+        for game in [GameKind::SkyrimSE, GameKind::Fallout4] {
+            let mut esm = ESMParser::cursor(&data);
+            esm.set_game(game);
+            let plugin = esm.parse_plugin()?;
+            let records: Vec<_> = plugin.records().collect();
+            assert!(matches!(records[0].get(b"CNAM"), Some(Value::U32(0x030201))));
+            assert!(matches!(records[1].get(b"LNAM"), Some(Value::FormId(FormId(1)))));
+            let reasons: Vec<_> = esm.diagnostics().iter().map(|e| e.to_string()).collect();
+            assert_eq!(reasons, ["field out of order in record at 0xac in GRUP FLST > FLST 0x00000002 > EDID"]);
+        }
+        Ok(())
+    }
+
+    fn compressed_record(type_id: &[u8;4], id: u32, fields: &[Vec<u8>], declared: u32) -> Vec<u8> {
+        use std::io::Write;
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&fields.concat()).unwrap();
+        let payload = [declared.to_le_bytes().to_vec(), encoder.finish().unwrap()].concat();
+        let mut record = record(type_id, id, &[]);
+        record[4..8].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+        record[8..12].copy_from_slice(&0x00040000u32.to_le_bytes()); // compressed
+        [record, payload].concat()
+    }
+
+    #[test]
+    fn compressed() -> super::Result<()> {
+        let fields = [field(b"EDID", b"Pistol\0"), field(b"ETYP", &2u32.to_le_bytes())];
+        let weap = compressed_record(b"WEAP", 0x00012345, &fields, 23);
+        let data = [tes4(0), group(b"WEAP", &[weap])].concat();
+
+        let plugin = ESMParser::cursor(&data).parse_plugin()?;
+        let record = plugin.records().next().unwrap();
+        assert_eq!(record.fields.len(), 2);
+        assert!(matches!(record.get(b"ETYP"), Some(Value::U32(2))));
+        Ok(())
+    }
+
+    #[test]
+    fn compressed_limits() {
+        let fields = [field(b"EDID", &[0; 1000])];
+        let weap = compressed_record(b"WEAP", 0x00012345, &fields, 100);
+        let data = [tes4(0), group(b"WEAP", &[weap])].concat();
+        let err = ESMParser::cursor(&data).parse_plugin().unwrap_err();
+        assert!(matches!(err, Error::Inflated { declared: 100, inflated: 101, .. }));
+
+        let weap = compressed_record(b"WEAP", 0x00012345, &fields, 1006);
+        let data = [tes4(0), group(b"WEAP", &[weap])].concat();
+        let mut esm = ESMParser::cursor(&data);
+        esm.set_limits(Limits { inflated: 1000, ..Limits::default() });
+        let err = esm.parse_plugin().unwrap_err();
+        assert!(matches!(err, Error::Limit { size: 1006, limit: 1000, .. }));
+        esm.set_limits(Limits { compressed: 8, ..Limits::default() });
+        let err = esm.parse_plugin().unwrap_err();
+        assert!(matches!(err, Error::Limit { limit: 8, .. }));
+
+        let mut weap = compressed_record(b"WEAP", 0x00012345, &fields, 1000);
+        weap[4..8].copy_from_slice(&(32u32 << 20).to_le_bytes()); // within the limits, past the group
+        let data = [tes4(0), group(b"WEAP", &[weap])].concat();
+        let err = ESMParser::cursor(&data).parse_plugin().unwrap_err();
+        assert_eq!(err.to_string(), "entry extends past the end of its group at 0x42 in GRUP WEAP > WEAP 0x00012345");
+    }
+
+    #[test]
+    fn malformed_strings() -> super::Result<()> {
+        let data = [
+            tes4(0),
+            group(b"GLOB", &[
+                record(b"GLOB", 1, &[field(b"EDID", b"Global")]),
+                record(b"GLOB", 2, &[field(b"EDID", b"Glo\0bal\0")]),
+                record(b"GLOB", 3, &[field(b"EDID", b"Gl\xF6bal\0")]),
+                record(b"GLOB", 4, &[field(b"EDID", b"Global\0")])
+            ])
+        ].concat();
+        let mut esm = ESMParser::cursor(&data);
+        esm.set_encoding(UTF_8);
+        let plugin = esm.parse_plugin()?;
+        let reasons: Vec<_> = esm.diagnostics().iter().map(|e| e.to_string()).collect();
+        assert_eq!(reasons, [
+            "string without a terminator at 0x5a in GRUP GLOB > GLOB 0x00000001 > EDID",
+            "string with an embedded NUL at 0x7e in GRUP GLOB > GLOB 0x00000002 > EDID",
+            "string is not valid in the plugin encoding at 0xa4 in GRUP GLOB > GLOB 0x00000003 > EDID"
+        ]);
+        let values: Vec<_> = plugin.records().map(|record| record.get(b"EDID").unwrap()).collect();
+        assert!(matches!(values[..3], [Value::RawString(_), Value::RawString(_), Value::RawString(_)]));
+        assert_eq!(format!("{:?}", values[3]), "\"Global\"");
+
+        let mut output = Vec::new();
+        Writer::new(&mut output).write_plugin(&plugin)?;
+        assert_eq!(output, data);
+        Ok(())
+    }
+
+    #[test]
+    fn encodings() -> super::Result<()> {
+        let data = [tes4(0), group(b"GLOB", &[record(b"GLOB", 1, &[field(b"EDID", b"Gl\xF6bal\0")])])].concat();
+        let mut esm = ESMParser::cursor(&data);
+        let plugin = esm.parse_plugin()?;
+        assert!(esm.diagnostics().is_empty());
+        assert_eq!(format!("{:?}", plugin.records().next().unwrap().get(b"EDID").unwrap()), "\"Glöbal\"");
+
+        let mut output = Vec::new();
+        Writer::new(&mut output).write_plugin(&plugin)?;
+        assert_eq!(output, data);
+
+        assert_eq!(encode_zstring("Глобал", WINDOWS_1251)?, b"\xC3\xEB\xEE\xE1\xE0\xEB\0");
+        assert!(encode_zstring("Глобал", WINDOWS_1252).is_err());
+
+        assert_eq!(default_encoding(GameKind::Fallout3, Some("russian")), WINDOWS_1251);
+        assert_eq!(default_encoding(GameKind::SkyrimSE, Some("Russian")), UTF_8);
+        assert_eq!(default_encoding(GameKind::Fallout4, Some("english")), WINDOWS_1252);
+        assert_eq!(default_encoding(GameKind::Fallout4, None), WINDOWS_1252);
+        Ok(())
+    }
+
+    #[test]
+    fn load_strings() -> super::Result<()> {
+        let dir = std::env::temp_dir().join(format!("esm-parser-{}-strings", std::process::id()));
+        std::fs::create_dir_all(dir.join("Strings"))?;
+        for kind in StringKind::ALL {
+            let table = string_table(&[(1, b"\xC3\xEB\xEE\xE1\xE0\xEB")], kind);
+            std::fs::write(dir.join(format!("Strings/Mod_russian.{}", kind.extension())), table)?;
+        }
+        let data = tes4(0x80);
+        let full = |esm: &ESMParser<_>| esm.strings.get(StringKind::Strings, 1).cloned();
+        let mut esm = ESMParser::cursor(&data);
+        esm.load_strings(dir.join("Mod.esm"), "russian")?;
+        assert_eq!(full(&esm).as_deref(), Some("Глобал"));
+        esm.set_encoding(WINDOWS_1252);
+        esm.load_strings(dir.join("Mod.esm"), "russian")?;
+        assert_eq!(full(&esm).as_deref(), Some("Ãëîáàë"));
+        assert!(esm.load_strings(dir.join("Other.esm"), "russian").is_err());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn oversized() -> super::Result<()> {
+        let ofst = vec![7; 70000];
+        let fields = [
+            field(b"EDID", b"World\0"),
+            field(b"XXXX", &(ofst.len() as u32).to_le_bytes()),
+            [&b"OFST"[..], &[0; 2], &ofst].concat(),
+            field(b"NAM2", &[1, 2, 3, 4])
+        ];
+        let edid = [vec![b'a'; 69999], vec![0]].concat();
+        let global = [field(b"XXXX", &(edid.len() as u32).to_le_bytes()), [&b"EDID"[..], &[0; 2], &edid].concat()];
+        let data = [
+            tes4(0),
+            group(b"WRLD", &[record(b"WRLD", 0x0000003C, &fields)]),
+            group(b"GLOB", &[record(b"GLOB", 0x0000003D, &global)])
+        ].concat();
+        let mut esm = ESMParser::cursor(&data);
+        let plugin = esm.parse_plugin()?;
+        assert!(esm.diagnostics().is_empty());
+        let records: Vec<_> = plugin.records().collect();
+        assert_eq!(records[0].fields.len(), 3);
+        assert!(matches!(records[0].get(b"OFST"), Some(Value::Unknown(bytes)) if bytes.len() == 70000));
+        assert!(records[0].get(b"NAM2").is_some());
+        assert!(matches!(records[1].get(b"EDID"), Some(Value::ZString(edid)) if edid.len() == 69999));
+
+        let mut output = Vec::new();
+        Writer::new(&mut output).write_plugin(&plugin)?;
+        assert_eq!(output, data);
+
+        let hostile = [field(b"XXXX", &u32::MAX.to_le_bytes()), [&b"OFST"[..], &[0; 2], &[7; 4]].concat()];
+        let data = [tes4(0), group(b"WRLD", &[record(b"WRLD", 0x0000003C, &hostile)])].concat();
+        let err = ESMParser::cursor(&data).parse_plugin().unwrap_err();
+        assert_eq!(err.to_string(), "field extends past the end of its record at 0x5a in GRUP WRLD > WRLD 0x0000003c > OFST");
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct Fields<'a>(Vec<(String, std::borrow::Cow<'a, [u8]>)>);
+
+    impl<'a> BorrowedVisitor<'a> for Fields<'a> {
+        fn field(&mut self, header: &FieldHeader, data: std::borrow::Cow<'a, [u8]>) -> super::Result<()> {
+            self.0.push((header.type_id.to_string(), data));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn borrowed() -> super::Result<()> {
+        let rifle = [field(b"EDID", b"Rifle\0"), field(b"XXXX", &4u32.to_le_bytes()), [&b"MODL"[..], &[0; 2], b"a.ni"].concat()];
+        let data = [
+            tes4(0),
+            group(b"WEAP", &[
+                record(b"WEAP", 1, &rifle),
+                compressed_record(b"WEAP", 2, &[field(b"EDID", b"Pistol\0")], 13)
+            ])
+        ].concat();
+        let mut esm = ESMParser::cursor(&data);
+        let mut fields = Fields::default();
+        esm.visit_borrowed(&mut fields)?;
+        let names: Vec<_> = fields.0.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["HEDR", "EDID", "MODL", "EDID"]);
+        assert!(matches!(fields.0[1].1, std::borrow::Cow::Borrowed(_)));
+        assert_eq!(zstr(&fields.0[1].1), Some("Rifle"));
+        assert_eq!(&fields.0[2].1[..], b"a.ni");
+        assert!(matches!(fields.0[3].1, std::borrow::Cow::Owned(_)));
+        assert_eq!(zstr(&fields.0[3].1), Some("Pistol"));
+        Ok(())
+    }
+
+    #[test]
+    fn zeta_mmap() -> super::Result<()> {
+        let plugin = ESMParser::mmap("data/Zeta.esm")?.parse_plugin()?;
+        let mut esm = ESMParser::mmap("data/Zeta.esm")?;
+        let mut fields = Fields::default();
+        esm.visit_borrowed(&mut fields)?;
+        let count = plugin.header.fields.len() + plugin.records().map(|record| record.fields.len()).sum::<usize>();
+        assert_eq!(fields.0.len(), count);
+        Ok(())
+    }
+
+    #[test]
+    fn zeta_parallel() -> super::Result<()> {
+        const DATA: &[u8] = include_bytes!("../data/Zeta.esm");
+        let sequential = ESMParser::cursor(DATA).parse_plugin()?;
+        let parallel = ESMParser::cursor(DATA).parse_plugin_parallel()?;
+        assert_eq!(format!("{:?}", parallel), format!("{:?}", sequential));
+        Ok(())
+    }
+
+    #[test]
+    fn parallel() -> super::Result<()> {
+        let broken = |id| record(b"GLOB", id, &[field(b"FLTV", &[0; 6])]);
+        let data = [
+            tes4(0),
+            group(b"GLOB", &[broken(1), record(b"GLOB", 2, &[field(b"EDID", b"Two\0")])]),
+            group(b"CLAS", &[record(b"CLAS", 3, &[field(b"EDID", b"Three\0")])]),
+            group(b"GLOB", &[broken(4)])
+        ].concat();
+        let mut esm = ESMParser::cursor(&data);
+        esm.set_lenient(true);
+        let sequential = esm.parse_plugin()?;
+        let expected: Vec<_> = esm.diagnostics().iter().map(|e| e.to_string()).collect();
+        let parallel = esm.parse_plugin_parallel()?;
+        let diagnostics: Vec<_> = esm.diagnostics().iter().map(|e| e.to_string()).collect();
+        assert_eq!(format!("{:?}", parallel), format!("{:?}", sequential));
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics, expected);
+
+        esm.set_lenient(false);
+        let err = esm.parse_plugin_parallel().unwrap_err();
+        assert_eq!(err.to_string(), esm.parse_plugin().unwrap_err().to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn zeta_stream() -> super::Result<()> {
+        const DATA: &[u8] = include_bytes!("../data/Zeta.esm");
+        let seekable = ESMParser::cursor(DATA).parse_plugin()?;
+        let mut esm = ESMParser::stream(DATA);
+        let streamed = esm.parse_plugin()?;
+        assert_eq!(format!("{:?}", streamed), format!("{:?}", seekable));
+        assert!(esm.get(FormId(0x00012345)).is_err()); // no random access
+
+        struct Unsized<'a>(std::io::Cursor<&'a [u8]>); // seekable, but cannot tell its size
+        impl std::io::Read for Unsized<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> { self.0.read(buf) }
+        }
+        impl std::io::Seek for Unsized<'_> {
+            fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+                match pos {
+                    std::io::SeekFrom::End(_) => Err(std::io::ErrorKind::Unsupported.into()),
+                    pos => self.0.seek(pos)
+                }
+            }
+        }
+        let err = ESMParser::new(Unsized(std::io::Cursor::new(DATA))).parse_plugin().unwrap_err();
+        assert!(matches!(err, Error::Io { .. })); // not silently parsed as a forward only stream
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn zeta_async() -> super::Result<()> {
+        use futures_util::TryStreamExt;
+        fn assert_send<T: Send>(_: &T) {}
+        let file = tokio::fs::File::open("data/Zeta.esm").await?;
+        let stream = AsyncParser::new(file).records();
+        assert_send(&stream);
+        let parsed: Vec<AsyncRecord> = stream.try_collect().await?;
+        assert!(parsed.iter().all(|parsed| parsed.diagnostics.is_empty()));
+        let plugin = ESMParser::file("data/Zeta.esm")?.parse_plugin()?;
+        let expected: Vec<_> = std::iter::once(&plugin.header).chain(plugin.records()).collect();
+        let records: Vec<_> = parsed.iter().map(|parsed| &parsed.record).collect();
+        assert_eq!(format!("{:?}", records), format!("{:?}", expected));
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn lenient_async() -> super::Result<()> {
+        use futures_util::TryStreamExt;
+        let data = [
+            tes4(0),
+            group(b"GLOB", &[
+                record(b"GLOB", 1, &[field(b"EDID", b"Global")]),
+                record(b"GLOB", 2, &[field(b"EDID", b"Broken\0"), field(b"FLTV", &[0; 6])]),
+                record(b"GLOB", 3, &[field(b"EDID", b"Intact\0")])
+            ])
+        ].concat();
+        assert!(AsyncParser::new(std::io::Cursor::new(&data)).records().try_collect::<Vec<_>>().await.is_err());
+
+        let mut esm = AsyncParser::new(std::io::Cursor::new(&data));
+        esm.set_lenient(true);
+        let parsed: Vec<AsyncRecord> = esm.records().try_collect().await?;
+        let mut sync = ESMParser::cursor(&data);
+        sync.set_lenient(true);
+        sync.parse_plugin()?;
+        assert_eq!(parsed.len(), 4);
+        let offsets = |diagnostics: &[Error]| diagnostics.iter().map(|e| e.context().offset).collect::<Vec<_>>();
+        let diagnostics: Vec<_> = parsed.iter().flat_map(|parsed| offsets(&parsed.diagnostics)).collect();
+        assert_eq!(diagnostics, offsets(sync.diagnostics()));
+        assert_eq!(parsed[2].diagnostics[0].to_string(), "expected 6 bytes, consumed 4 at 0x8b in GLOB 0x00000002 > FLTV");
+        assert_eq!(parsed[2].record.fields.len(), 1);
+        assert!(parsed[3].diagnostics.is_empty());
+
+        let mut glob = record(b"GLOB", 4, &[field(b"EDID", b"Global\0")]);
+        glob[4..8].copy_from_slice(&(128u32 << 20).to_le_bytes()); // within the limits, past the group
+        let data = [tes4(0), group(b"GLOB", &[glob]), group(b"WEAP", &[])].concat();
+        let err = AsyncParser::new(std::io::Cursor::new(&data)).records().try_collect::<Vec<_>>().await.unwrap_err();
+        assert_eq!(err.to_string(), "entry extends past the end of its group at 0x42");
+        Ok(())
+    }
+
+    #[test]
+    fn stateful_callbacks() -> super::Result<()> {
+        const DATA: &[u8] = include_bytes!("../data/Zeta.esm");
+        let plugin = ESMParser::cursor(DATA).parse_plugin()?;
+        let (mut groups, mut records, mut fields) = (0, 0, Vec::new());
+        ESMParser::cursor(DATA).parse_top_level(|parser, header, visitor| match &header.type_id.0 {
+            b"TES4" => parser.parse_fields(|parser, header| {
+                fields.push(header.type_id.0);
+                parser.raw_field(header)
+            }, header.size, visitor),
+            _ => {
+                groups += 1;
+                parser.parse_records(|parser, header, _| {
+                    records += 1;
+                    Ok(parser.skip(header.size as u64)?)
+                }, header.size as u64, visitor)
+            }
+        }, &mut ())?;
+        assert_eq!(groups, plugin.groups.len());
+        assert_eq!(records, plugin.records().count());
+        assert_eq!(fields, plugin.header.fields.iter().map(|field| field.header.type_id.0).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn decoders() -> super::Result<()> {
+        const DATA: &[u8] = include_bytes!("../data/Zeta.esm");
+        let plugin = ESMParser::cursor(DATA).parse_plugin()?;
+        let first = plugin.records().next().unwrap();
+        let (type_id, field_id) = (first.header.type_id.0, first.fields[0].header.type_id.0);
+        let mut esm = ESMParser::cursor(DATA);
+        esm.register_decoder(&type_id, &field_id, |field, data| {
+            assert_eq!(field.header.size as usize, data.len());
+            Ok(Value::U32(data.len() as u32))
+        });
+        let custom = esm.parse_plugin()?;
+        for (record, expected) in custom.records().zip(plugin.records()) {
+            for (field, expected) in record.fields.iter().zip(&expected.fields) {
+                match (record.header.type_id.0, field.header.type_id.0) == (type_id, field_id) {
+                    true => assert!(matches!(field.value, Value::U32(size) if size == expected.header.size as u32)),
+                    false => assert_eq!(format!("{:?}", field.value), format!("{:?}", expected.value))
+                }
+            }
+        }
+        let found = esm.get(first.form_id())?.unwrap();
+        assert!(matches!(found.fields[0].value, Value::U32(_)));
+
+        let data = [
+            tes4(0),
+            group(b"GLOB", &[record(b"GLOB", 1, &[field(b"EDID", b"Global\0"), field(b"FLTV", &1.5f32.to_le_bytes())])])
+        ].concat();
+        let mut esm = ESMParser::cursor(&data);
+        assert!(esm.decoders().get(b"WEAP", b"DATA").is_some());
+        assert!(esm.decoders().registered(b"WEAP", b"DATA").is_none());
+        let builtin = esm.decoders().get(b"GLOB", b"EDID").unwrap();
+        esm.register_decoder(b"GLOB", b"EDID", move |field, data| match builtin(field, data)? {
+            Value::ZString(edid) => Ok(Value::ZString(edid.to_uppercase())),
+            value => Ok(value)
+        });
+        let plugin = esm.parse_plugin()?;
+        let global = plugin.records().next().unwrap();
+        assert_eq!(format!("{:?}", global.get(b"EDID").unwrap()), "\"GLOBAL\"");
+        assert!(matches!(global.get(b"FLTV"), Some(Value::F32(value)) if *value == 1.5));
+
+        let mut edids = Vec::new(); // explicit callbacks are not replaced by the registry
+        esm.parse_top_level(|parser, header, visitor| match &header.type_id.0 {
+            b"GRUP" => parser.parse_records(|parser, header, visitor| {
+                parser.parse_fields(|parser, header| {
+                    let value = parser.raw_field(header)?;
+                    if header.type_id == b"EDID" { edids.push(format!("{:?}", value)); }
+                    Ok(value)
+                }, header.size, visitor)
+            }, header.size as u64, visitor),
+            _ => Ok(parser.skip(header.size as u64)?)
+        }, &mut ())?;
+        assert_eq!(edids, ["Unknown [71, 108, 111, 98, 97, 108, 0]"]);
+        Ok(())
+    }
+
+    #[test]
+    fn zeta_dump() -> super::Result<()> {
+        const DATA: &[u8] = include_bytes!("../data/Zeta.esm");
+        let mut esm = ESMParser::cursor(DATA);
+        let mut output = Vec::new();
+        esm.parse_top_level(ESMParser::TES4, &mut Dump::new(&mut output))?;
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("RecordHeader"));
+        assert!(output.contains("\n  FieldHeader"));
+        Ok(())
+    }
+
+    /// Output shared with the test while the parser owns the writer.
+    #[derive(Clone, Default)]
+    struct Shared(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> { self.0.lock().unwrap().write(buf) }
+        fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+    }
+
+    #[test]
+    fn zeta_output() -> super::Result<()> {
+        fn assert_send<T: Send>() {}
+        assert_send::<ESMParser<std::fs::File>>();
+
+        const DATA: &[u8] = include_bytes!("../data/Zeta.esm");
+        let mut expected = Vec::new();
+        ESMParser::cursor(DATA).parse_top_level(ESMParser::TES4, &mut Dump::new(&mut expected))?;
+        let output = Shared::default();
+        let mut esm = ESMParser::cursor(DATA);
+        esm.set_output(output.clone());
+        esm.dump()?;
+        assert_eq!(*output.0.lock().unwrap(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn zeta_plugin() -> super::Result<()> {
         const DATA: &[u8] = include_bytes!("../data/Zeta.esm");
         let mut esm = ESMParser::cursor(DATA);
-        esm.parse_top_level(ESMParser::TES4)
+        let plugin = esm.parse_plugin()?;
+        assert!(plugin.header.get(b"HEDR").is_some());
+        assert!(plugin.records().all(|record| record.header.type_id != b"GRUP"));
+        Ok(())
     }
 }
@@ -0,0 +1,174 @@
+//! Several plugins parsed in load order.
+
+use crate::error::{Error, Result};
+use crate::form_id::*;
+use crate::model::*;
+use crate::ESMParser;
+
+use std::collections::HashMap;
+
+//------------------------------------------------------------------------------
+
+/// Plugin of a load order with the masters its form IDs refer to.
+#[derive(Debug)]
+pub struct LoadedPlugin {
+    pub masters: Masters,
+    pub plugin: Plugin
+}
+
+impl LoadedPlugin {
+    /// File name of the plugin.
+    pub fn name(&self) -> &str { self.masters.plugin() }
+}
+
+/// Plugin defining a global ID and where its record is within the plugin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definition {
+    /// Load order position of the plugin.
+    pub position: usize,
+    /// Entry indices from the top level group down to the record.
+    entries: Box<[u32]>
+}
+
+/// Plugins loaded in order, the last plugin defining a record wins.
+///
+/// Global form IDs replace the master byte of a plugin's IDs by the load order
+/// position of the plugin the ID refers to.
+#[derive(Debug, Default)]
+pub struct LoadOrder {
+    plugins: Vec<LoadedPlugin>,
+    names: Vec<String>,
+    /// Plugins defining each global ID, in load order.
+    definitions: HashMap<FormId, Vec<Definition>>
+}
+
+impl LoadOrder {
+    pub fn new() -> Self { LoadOrder::default() }
+
+    /// Parse the plugins at `paths` in order.
+    pub fn open<P: AsRef<std::path::Path>>(paths: &[P]) -> Result<Self> {
+        let mut load_order = LoadOrder::new();
+        for path in paths {
+            let path = path.as_ref();
+            let name = path.file_name().ok_or(Error::invalid("plugin path without a file name"))?;
+            let plugin = ESMParser::file(path)?.parse_plugin()?;
+            load_order.push(name.to_string_lossy(), plugin)?;
+        }
+        Ok(load_order)
+    }
+
+    /// Append a parsed plugin named `name`, whose masters must already be
+    /// loaded. Of the records a plugin defines twice, the last one counts.
+    pub fn push(&mut self, name: impl Into<String>, plugin: Plugin) -> Result<()> {
+        let masters = Masters::of(&plugin, name);
+        if self.names.len() >= 0xFF { return Err(Error::invalid("load order of more than 255 plugins")) }
+        if self.position(masters.plugin()).is_some() { return Err(Error::invalid("plugin loaded twice")) }
+        if let Some(master) = masters.masters().iter().find(|master| self.position(master).is_none()) {
+            return Err(Error::master(masters.plugin(), master))
+        }
+        let position = self.plugins.len();
+        self.names.push(masters.plugin().to_owned());
+        let ids = locate(&plugin).into_iter()
+            .map(|(entries, record)| Ok((masters.to_global(record.form_id(), &self.names)?, entries)))
+            .collect::<Result<Vec<_>>>();
+        let ids = match ids {
+            Ok(ids) => ids,
+            Err(error) => { // a record of a master the plugin does not list
+                self.names.pop();
+                return Err(error)
+            }
+        };
+        for (id, entries) in ids {
+            let definitions = self.definitions.entry(id).or_default();
+            if definitions.last().is_some_and(|definition| definition.position == position) { definitions.pop(); }
+            definitions.push(Definition { position, entries });
+        }
+        self.plugins.push(LoadedPlugin { masters, plugin });
+        Ok(())
+    }
+
+    pub fn plugins(&self) -> &[LoadedPlugin] { &self.plugins }
+
+    /// Load order position of a plugin, names compare case insensitively.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|loaded| loaded.eq_ignore_ascii_case(name))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LoadedPlugin> {
+        self.position(name).map(|position| &self.plugins[position])
+    }
+
+    /// Global ID of an ID stored in the plugin at `position`.
+    pub fn to_global(&self, position: usize, id: FormId) -> Result<FormId> {
+        let plugin = self.plugins.get(position).ok_or(Error::invalid("plugin outside the load order"))?;
+        plugin.masters.to_global(id, &self.names)
+    }
+
+    /// ID as stored in the plugin at `position`, which must have the plugin the
+    /// global ID refers to as a master.
+    pub fn to_local(&self, position: usize, id: FormId) -> Result<FormId> {
+        let plugin = self.plugins.get(position).ok_or(Error::invalid("plugin outside the load order"))?;
+        plugin.masters.from_global(id, &self.names)
+    }
+
+    /// Every record with its global ID and the position of its plugin, in load
+    /// order.
+    pub fn records(&self) -> impl Iterator<Item = (FormId, usize, &Record)> {
+        self.plugins.iter().enumerate().flat_map(move |(position, loaded)| {
+            loaded.plugin.records().map(move |record| {
+                let id = loaded.masters.to_global(record.form_id(), &self.names).unwrap(); // checked by push
+                (id, position, record)
+            })
+        })
+    }
+
+    /// Plugins defining a global ID, the original first and the overrides in
+    /// load order.
+    pub fn definitions(&self, id: FormId) -> &[Definition] {
+        self.definitions.get(&id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Plugin whose version of a global ID wins, the last one defining it.
+    pub fn winner(&self, id: FormId) -> Option<&LoadedPlugin> {
+        self.definitions(id).last().map(|definition| &self.plugins[definition.position])
+    }
+
+    /// Winning version of the record with a global ID.
+    pub fn winning_record(&self, id: FormId) -> Option<&Record> {
+        self.definitions(id).last().and_then(|definition| self.record(definition))
+    }
+
+    /// Record of a definition.
+    pub fn record(&self, definition: &Definition) -> Option<&Record> {
+        let plugin = &self.plugins.get(definition.position)?.plugin;
+        let (&first, entries) = definition.entries.split_first()?;
+        let mut group = plugin.groups.get(first as usize)?;
+        for (depth, &index) in entries.iter().enumerate() {
+            match group.entries.get(index as usize)? {
+                Entry::Group(child) => group = child,
+                Entry::Record(record) => return (depth + 1 == entries.len()).then_some(record)
+            }
+        }
+        None
+    }
+}
+
+/// Every record of a plugin with the entry indices leading to it, in the order
+/// of `Plugin::records`.
+fn locate(plugin: &Plugin) -> Vec<(Box<[u32]>, &Record)> {
+    fn walk<'a>(group: &'a Group, path: &mut Vec<u32>, records: &mut Vec<(Box<[u32]>, &'a Record)>) {
+        for (index, entry) in group.entries.iter().enumerate() {
+            path.push(index as u32);
+            match entry {
+                Entry::Group(group) => walk(group, path, records),
+                Entry::Record(record) => records.push((path.as_slice().into(), record))
+            }
+            path.pop();
+        }
+    }
+    let mut records = Vec::new();
+    for (index, group) in plugin.groups.iter().enumerate() {
+        walk(group, &mut vec![index as u32], &mut records);
+    }
+    records
+}
@@ -0,0 +1,159 @@
+//! Zero-copy parsing of memory mapped plugins.
+
+use chunk_parser::prelude::*;
+use esm_bindings::fo3::*;
+
+use crate::error::{Error, Result};
+use crate::game::GameKind;
+use crate::{ESMParser, Limits};
+
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::ops::Range;
+
+use memmap2::Mmap;
+
+//------------------------------------------------------------------------------
+
+/// Receiver of parse events with field payloads borrowed from the plugin data.
+///
+/// Payloads are borrowed for the lifetime of the data, except for fields of
+/// compressed records, which are copied out of the inflated record.
+#[allow(unused_variables)]
+pub trait BorrowedVisitor<'a> {
+    /// A group starts, its children follow until the matching `leave_group`.
+    fn enter_group(&mut self, header: &GroupHeader) -> Result<()> { Ok(()) }
+
+    /// A group ends.
+    fn leave_group(&mut self, header: &GroupHeader) -> Result<()> { Ok(()) }
+
+    /// A record starts, its fields follow until the matching `leave_record`.
+    fn record(&mut self, header: &RecordHeader) -> Result<()> { Ok(()) }
+
+    /// A record ends.
+    fn leave_record(&mut self, header: &RecordHeader) -> Result<()> { Ok(()) }
+
+    /// A field of the current record, undecoded.
+    fn field(&mut self, header: &FieldHeader, data: Cow<'a, [u8]>) -> Result<()> { Ok(()) }
+}
+
+/// View a zero terminated field as a string, if it is valid UTF-8 without
+/// embedded NULs. Other codepages need `decode_zstring`.
+pub fn zstr(data: &[u8]) -> Option<&str> {
+    let (&0, string) = data.split_last()? else { return None };
+    if string.contains(&0) { return None }
+    std::str::from_utf8(string).ok()
+}
+
+//------------------------------------------------------------------------------
+
+impl ESMParser<Cursor<Mmap>> {
+    /// Parse a plugin from a read only memory map of the file.
+    pub fn mmap(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let map = unsafe { Mmap::map(&file)? }; // plugins are not expected to change while parsed
+        Ok(ESMParser::new(Cursor::new(map)))
+    }
+}
+
+impl<T> ESMParser<Cursor<T>> where T: AsRef<[u8]> {
+    /// Walk the whole plugin without decoding or copying the fields.
+    pub fn visit_borrowed<'a>(&'a mut self, visitor: &mut dyn BorrowedVisitor<'a>) -> Result<()> {
+        let game = match self.game() {
+            Some(game) => game,
+            None => self.detect_game()?
+        };
+        let limits = self.limits;
+        let data: &'a [u8] = self.reader().get_ref().as_ref();
+        Walker { game, limits, visitor }.records(data, 0)
+    }
+}
+
+struct Walker<'a, 'v> {
+    game: GameKind,
+    limits: Limits,
+    visitor: &'v mut dyn BorrowedVisitor<'a>
+}
+
+impl<'a> Walker<'a, '_> {
+    /// Walk the records and groups in `data`, found at `base` in the file.
+    fn records(&mut self, data: &'a [u8], base: u64) -> Result<()> {
+        let header_size = self.game.header_size();
+        let mut pos = 0;
+        while pos < data.len() {
+            let offset = base + pos as u64;
+            let mut raw = [0u8; 24];
+            raw[..header_size].copy_from_slice(slice(data, pos..pos + header_size, offset)?);
+            let header: RecordHeader = unsafe { std::mem::transmute(raw) };
+            pos += header_size;
+            if header.type_id == b"GRUP" {
+                let size = (header.size as usize).checked_sub(header_size)
+                    .ok_or_else(|| Error::invalid("group smaller than its header").at(offset))?;
+                let body = slice(data, pos..pos + size, offset)?;
+                let group: GroupHeader = unsafe { std::mem::transmute(header) };
+                self.visitor.enter_group(&group)?;
+                self.records(body, base + pos as u64)?;
+                self.visitor.leave_group(&group)?;
+                pos += size;
+            } else {
+                let size = header.size as usize;
+                let body = slice(data, pos..pos + size, offset)?;
+                self.visitor.record(&header)?;
+                if (header.flags & 0x00040000) != 0 {
+                    self.compressed(body, offset)?;
+                } else {
+                    let mut fields = 0;
+                    while let Some((header, range)) = next_field(body, &mut fields, offset)? {
+                        self.visitor.field(&header, Cow::Borrowed(&body[range]))?;
+                    }
+                }
+                self.visitor.leave_record(&header)?;
+                pos += size;
+            }
+        }
+        Ok(())
+    }
+
+    /// Inflate a compressed record and walk copies of its fields.
+    fn compressed(&mut self, body: &[u8], offset: u64) -> Result<()> {
+        let size = slice(body, 0..4, offset)?;
+        let declared = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as u64;
+        let compressed = &body[4..];
+        if compressed.len() as u64 > self.limits.compressed {
+            return Err(Error::limit("compressed record", compressed.len() as u64, self.limits.compressed).at(offset))
+        }
+        let inflated = crate::inflate(compressed, declared, self.limits.inflated).map_err(|e| e.at(offset))?;
+        let mut pos = 0;
+        while let Some((header, range)) = next_field(&inflated, &mut pos, offset)? {
+            self.visitor.field(&header, Cow::Owned(inflated[range].to_vec()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Bounds checked sub-slice, reporting truncation at `offset`.
+fn slice(data: &[u8], range: Range<usize>, offset: u64) -> Result<&[u8]> {
+    data.get(range).ok_or_else(|| Error::invalid("truncated record").at(offset))
+}
+
+/// Next field header and payload range, folding `XXXX` into the field it sizes.
+fn next_field(data: &[u8], pos: &mut usize, offset: u64) -> Result<Option<(FieldHeader, Range<usize>)>> {
+    if *pos >= data.len() { return Ok(None) }
+    let read_header = |pos: usize| -> Result<FieldHeader> {
+        let raw: [u8; 6] = slice(data, pos..pos + 6, offset)?.try_into().unwrap();
+        Ok(unsafe { std::mem::transmute::<[u8; 6], FieldHeader>(raw) })
+    };
+    let mut header = read_header(*pos)?;
+    *pos += 6;
+    let mut size = header.size as usize;
+    if header.type_id == b"XXXX" { // size of a field larger than 64 KiB
+        let raw = slice(data, *pos..*pos + 4, offset)?;
+        size = u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as usize;
+        header = read_header(*pos + 4)?;
+        *pos += 10;
+    }
+    let range = *pos..*pos + size;
+    slice(data, range.clone(), offset)?;
+    *pos += size;
+    Ok(Some((header, range)))
+}
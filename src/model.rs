@@ -0,0 +1,284 @@
+//! Owned plugin model produced by the parser.
+
+use esm_bindings::fo3::*;
+
+use crate::encoding::{Encoding, WINDOWS_1252};
+use crate::error::{Error, Result};
+use crate::field::FieldValue;
+use crate::form_id::FormId;
+use crate::game::GameKind;
+use crate::visitor::Visitor;
+use crate::writer::Encode;
+
+//------------------------------------------------------------------------------
+
+/// Parsed plugin, the `TES4` header record followed by the top level groups.
+#[derive(Debug)]
+pub struct Plugin {
+    /// Game selecting the header layout used by the writer.
+    pub game: GameKind,
+    /// Codepage the writer encodes strings with.
+    pub encoding: &'static Encoding,
+    pub header: Record,
+    pub groups: Vec<Group>
+}
+
+/// Group of records and sub-groups.
+#[derive(Debug)]
+pub struct Group {
+    pub header: GroupHeader,
+    pub entries: Vec<Entry>
+}
+
+/// Child of a group.
+#[derive(Debug)]
+pub enum Entry {
+    Group(Group),
+    Record(Record)
+}
+
+/// Record and its decoded fields.
+#[derive(Debug)]
+pub struct Record {
+    pub header: RecordHeader,
+    pub fields: Vec<Field>,
+    /// Original zlib stream of a compressed record, reused by the writer while
+    /// it still inflates to the encoded fields.
+    pub compressed: Option<Vec<u8>>
+}
+
+/// Field header and its decoded value.
+#[derive(Debug)]
+pub struct Field {
+    pub header: FieldHeader,
+    pub value: Value
+}
+
+macro_rules! value {
+    ($($(#[$meta:meta])* $variant:ident($ty:ty)),* $(,)?) => {
+        /// Decoded field payload.
+        pub enum Value {
+            $($(#[$meta])* $variant($ty),)*
+            /// Zero sized marker field.
+            Empty,
+            /// Raw payload of a field without a decoder.
+            Unknown(Vec<u8>)
+        }
+
+        /// Values print as their payload, without the variant name.
+        impl std::fmt::Debug for Value {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $(Value::$variant(v) => v.fmt(f),)*
+                    Value::Empty => Ok(()),
+                    Value::Unknown(v) => write!(f, "Unknown {:?}", v)
+                }
+            }
+        }
+
+        impl Value {
+            /// Append the encoded payload.
+            pub fn encode(&self, out: &mut Vec<u8>) {
+                match self {
+                    $(Value::$variant(v) => v.encode(out),)*
+                    Value::Empty => {},
+                    Value::Unknown(v) => out.extend_from_slice(v)
+                }
+            }
+        }
+    };
+}
+
+value! {
+    ZString(String),
+    /// String which may be localised.
+    LString(String),
+    /// String of a localised plugin, stored in a string table.
+    Localised(LocalisedString),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    FormId(FormId),
+    /// String which did not decode, kept as raw bytes.
+    RawString(Vec<u8>),
+    /// Fixed size payload without a binding struct.
+    Bytes(Vec<u8>),
+    /// Payload of a struct deriving `EsmField`.
+    Struct(Box<dyn FieldValue>),
+    ATTR(ATTR),
+    CLAS(CLAS),
+    CNTO(CNTO),
+    COED(COED),
+    CRDT(CRDT),
+    CTDA(CTDA),
+    DATA(DATA),
+    DNAM(DNAM),
+    DSTD(DSTD),
+    EFIT(EFIT),
+    ENIT(ENIT),
+    HEDR(HEDR),
+    MGEF(MGEF),
+    OBND(OBND),
+    SCHR(SCHR),
+    SNDD(SNDD),
+    SPIT(SPIT),
+    XNAM(XNAM),
+}
+
+/// String table reference, with the string if the tables contained it.
+#[derive(Clone)]
+pub struct LocalisedString {
+    pub id: u32,
+    pub string: Option<String>
+}
+
+impl std::fmt::Debug for LocalisedString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.string {
+            Some(string) => string.fmt(f),
+            None => write!(f, "<missing string {:#010x}>", self.id)
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+
+impl Plugin {
+    /// Iterate over every record in the plugin, depth first.
+    pub fn records(&self) -> impl Iterator<Item = &Record> {
+        self.groups.iter().flat_map(|group| group.records())
+    }
+}
+
+impl Group {
+    /// Iterate over every record in the group, depth first.
+    pub fn records(&self) -> Box<dyn Iterator<Item = &Record> + '_> {
+        Box::new(self.entries.iter().flat_map(|entry| match entry {
+            Entry::Group(group) => group.records(),
+            Entry::Record(record) => Box::new(std::iter::once(record))
+        }))
+    }
+}
+
+impl Record {
+    /// First field with the given type.
+    pub fn get(&self, type_id: &[u8;4]) -> Option<&Value> {
+        self.fields.iter().find(|field| field.header.type_id == type_id).map(|field| &field.value)
+    }
+
+    /// ID of the record, relative to the masters of its plugin.
+    pub fn form_id(&self) -> FormId { FormId(self.header.id) }
+}
+
+//------------------------------------------------------------------------------
+
+/// Visitor collecting the parse events into a `Plugin`.
+#[derive(Default)]
+pub struct PluginBuilder {
+    header: Option<Record>,
+    groups: Vec<Group>,
+    stack: Vec<Group>
+}
+
+impl PluginBuilder {
+    /// Finish the plugin, failing if the `TES4` header was never seen.
+    pub fn finish(self) -> Result<Plugin> {
+        match self.header {
+            Some(header) if self.stack.is_empty() => Ok(Plugin {
+                game: GameKind::default(), encoding: WINDOWS_1252, header, groups: self.groups
+            }),
+            Some(_) => Err(Error::invalid("unterminated group")),
+            None => Err(Error::invalid("missing TES4 header"))
+        }
+    }
+
+    /// Append the header and groups collected by another builder, which was run
+    /// on a later part of the same plugin.
+    pub fn append(&mut self, other: PluginBuilder) -> Result<()> {
+        if !other.stack.is_empty() { return Err(Error::invalid("unterminated group")) }
+        if let Some(header) = other.header {
+            if self.header.is_some() { return Err(Error::invalid("duplicate TES4 header")) }
+            self.header = Some(header);
+        }
+        self.groups.extend(other.groups);
+        Ok(())
+    }
+
+    /// Record the next field belongs to.
+    fn current(&mut self) -> Result<&mut Record> {
+        match self.stack.last_mut() {
+            Some(group) => match group.entries.last_mut() {
+                Some(Entry::Record(record)) => Ok(record),
+                _ => Err(Error::invalid("field outside of a record"))
+            },
+            None => self.header.as_mut().ok_or(Error::invalid("field outside of a record"))
+        }
+    }
+}
+
+impl Visitor for PluginBuilder {
+    fn enter_group(&mut self, header: &GroupHeader) -> Result<()> {
+        self.stack.push(Group { header: *header, entries: Vec::new() });
+        Ok(())
+    }
+
+    fn leave_group(&mut self, _header: &GroupHeader) -> Result<()> {
+        let group = self.stack.pop().ok_or(Error::invalid("unbalanced group"))?;
+        match self.stack.last_mut() {
+            Some(parent) => parent.entries.push(Entry::Group(group)),
+            None => self.groups.push(group)
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, header: &RecordHeader) -> Result<()> {
+        let record = Record { header: *header, fields: Vec::new(), compressed: None };
+        match self.stack.last_mut() {
+            Some(group) => group.entries.push(Entry::Record(record)),
+            None if self.header.is_none() && header.type_id == b"TES4" => self.header = Some(record),
+            None => return Err(Error::invalid("record outside of a group"))
+        }
+        Ok(())
+    }
+
+    fn compressed(&mut self, data: &[u8]) -> Result<()> {
+        self.current()?.compressed = Some(data.to_vec());
+        Ok(())
+    }
+
+    fn field(&mut self, header: &FieldHeader, value: Value) -> Result<()> {
+        self.current()?.fields.push(Field { header: *header, value });
+        Ok(())
+    }
+}
+
+/// Visitor collecting a single record, used for random access.
+#[derive(Default)]
+pub struct RecordBuilder(Option<Record>);
+
+impl RecordBuilder {
+    pub fn finish(self) -> Result<Record> {
+        self.0.ok_or(Error::invalid("missing record"))
+    }
+}
+
+impl Visitor for RecordBuilder {
+    fn record(&mut self, header: &RecordHeader) -> Result<()> {
+        self.0 = Some(Record { header: *header, fields: Vec::new(), compressed: None });
+        Ok(())
+    }
+
+    fn compressed(&mut self, data: &[u8]) -> Result<()> {
+        let record = self.0.as_mut().ok_or(Error::invalid("field outside of a record"))?;
+        record.compressed = Some(data.to_vec());
+        Ok(())
+    }
+
+    fn field(&mut self, header: &FieldHeader, value: Value) -> Result<()> {
+        let record = self.0.as_mut().ok_or(Error::invalid("field outside of a record"))?;
+        record.fields.push(Field { header: *header, value });
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+//! Parallel parsing of top level groups.
+
+use chunk_parser::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::model::*;
+use crate::visitor::Visitor;
+use crate::ESMParser;
+
+use std::io::{Cursor, Seek, SeekFrom};
+
+use rayon::prelude::*;
+
+//------------------------------------------------------------------------------
+
+impl<T> ESMParser<Cursor<T>> where T: AsRef<[u8]> {
+    /// Parse every top level group on its own thread, with one visitor per top
+    /// level record or group, returned in file order.
+    ///
+    /// The `TES4` header is parsed first, so the groups see its flags. Errors and
+    /// diagnostics are the same as for `parse_top_level`.
+    pub fn parse_top_level_parallel<V>(&mut self, visitor: impl Fn() -> V + Sync) -> Result<Vec<V>>
+    where V: Visitor + Send {
+        if self.game.is_none() { self.detect_game()?; }
+        self.diagnostics.clear();
+        let entries = self.top_level()?;
+        let Some(&(offset, size)) = entries.first() else { return Ok(Vec::new()) };
+
+        let mut visitors = vec![visitor()];
+        self.reader().seek(SeekFrom::Start(offset))?;
+        self.parse_records(ESMParser::TES4, size, &mut visitors[0])?;
+
+        let options = self.options();
+        let data: &[u8] = self.reader().get_ref().as_ref();
+        let results: Vec<Result<(V, Vec<Error>)>> = entries[1..].par_iter().map(|&(offset, size)| {
+            let mut parser = ESMParser::cursor(data);
+            parser.apply(options.clone());
+            parser.reader().seek(SeekFrom::Start(offset))?;
+            let mut visitor = visitor();
+            parser.parse_records(ESMParser::TES4, size, &mut visitor)?;
+            Ok((visitor, parser.diagnostics))
+        }).collect();
+
+        for result in results {
+            let (visitor, diagnostics) = result?; // first error in file order
+            visitors.push(visitor);
+            self.diagnostics.extend(diagnostics);
+        }
+        Ok(visitors)
+    }
+
+    /// Parse the whole file into a `Plugin`, decoding the groups in parallel.
+    pub fn parse_plugin_parallel(&mut self) -> Result<Plugin> {
+        let mut builder = PluginBuilder::default();
+        for other in self.parse_top_level_parallel(PluginBuilder::default)? {
+            builder.append(other)?;
+        }
+        self.finish_plugin(builder)
+    }
+
+    /// Offset and size of every top level record and group.
+    fn top_level(&mut self) -> Result<Vec<(u64, u64)>> {
+        let end = self.reader().seek(SeekFrom::End(0))?;
+        let mut offset = self.reader().seek(SeekFrom::Start(0))?;
+        let header_size = self.layout().header_size() as u64;
+        let mut entries = Vec::new();
+        while offset < end {
+            let header = self.read_header()?;
+            let mut size = header.size as u64;
+            if header.type_id != b"GRUP" {
+                size += header_size;
+            } else if size < header_size {
+                return Err(Error::invalid("group smaller than its header").at(offset))
+            }
+            entries.push((offset, size));
+            offset = self.reader().seek(SeekFrom::Start(offset + size))?;
+        }
+        Ok(entries)
+    }
+}
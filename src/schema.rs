@@ -0,0 +1,258 @@
+//! Declarative record layouts and the engine decoding fields with them.
+
+use chunk_parser::prelude::*;
+use esm_bindings::fo3::*;
+
+use crate::decoders::FieldContext;
+use crate::encoding::{decode_zstring, Encoding};
+use crate::error::{Error, Result};
+use crate::field::EsmField;
+use crate::form_id::FormId;
+use crate::model::*;
+use crate::strings::StringKind;
+use crate::visitor::Visitor;
+use crate::ESMParser;
+
+//------------------------------------------------------------------------------
+
+macro_rules! kind {
+    ($($binding:ident),* $(,)?) => {
+        /// How a field payload is decoded.
+        #[derive(Debug, Clone, Copy)]
+        pub enum Kind {
+            /// Zero terminated string.
+            ZString,
+            /// String stored in a string table by localised plugins.
+            LString,
+            U8,
+            U16,
+            U32,
+            U64,
+            F32,
+            FormId,
+            /// Fixed number of bytes without a binding struct.
+            Bytes(usize),
+            /// Zero sized marker field.
+            Empty,
+            /// Struct deriving `EsmField`, written `Struct(Type)` in schemas.
+            Struct(fn(&[u8], u16) -> Result<Value>),
+            $($binding,)*
+        }
+
+        impl<R> ESMParser<R> where R: std::io::Read + std::io::Seek {
+            /// Decode a field payload as `kind`, of a record with the given
+            /// form version.
+            fn decode(&mut self, kind: Kind, header: &FieldHeader, version: u16) -> Result<Value> {
+                Ok(match kind {
+                    Kind::ZString => self.read_zstring(self.field_size(header))?,
+                    Kind::LString => self.read_lstring(header)?,
+                    Kind::U8 => Value::U8(self.read()?),
+                    Kind::U16 => Value::U16(self.read()?),
+                    Kind::U32 => Value::U32(self.read()?),
+                    Kind::U64 => Value::U64(self.read()?),
+                    Kind::F32 => Value::F32(self.read()?),
+                    Kind::FormId => Value::FormId(FormId(self.read()?)),
+                    Kind::Bytes(size) => Value::Bytes(self.read_bytes(size)?),
+                    Kind::Empty => Value::Empty,
+                    Kind::Struct(decode) => decode(&self.read_bytes(self.field_size(header))?, version)?,
+                    $(Kind::$binding => Value::$binding(self.read()?),)*
+                })
+            }
+        }
+
+        impl Kind {
+            /// Decode a whole field payload as this kind, see `Decoders::builtin`.
+            pub fn decode_bytes(self, context: &FieldContext, data: &[u8]) -> Result<Value> {
+                let version = context.version;
+                Ok(match self {
+                    Kind::ZString => string(data, context.encoding, Value::ZString),
+                    Kind::LString => match context.strings {
+                        Some(strings) => {
+                            let id = u32::decode(data, version)?;
+                            let string = strings.get(StringKind::of(&context.header.type_id.0), id).cloned();
+                            Value::Localised(LocalisedString { id, string })
+                        },
+                        None => string(data, context.encoding, Value::LString)
+                    },
+                    Kind::U8 => Value::U8(u8::decode(data, version)?),
+                    Kind::U16 => Value::U16(u16::decode(data, version)?),
+                    Kind::U32 => Value::U32(u32::decode(data, version)?),
+                    Kind::U64 => Value::U64(u64::decode(data, version)?),
+                    Kind::F32 => Value::F32(f32::decode(data, version)?),
+                    Kind::FormId => Value::FormId(FormId(u32::decode(data, version)?)),
+                    Kind::Bytes(size) => Value::Bytes(exact(data, size)?.to_vec()),
+                    Kind::Empty => { exact(data, 0)?; Value::Empty },
+                    Kind::Struct(decode) => decode(data, version)?,
+                    $(Kind::$binding => {
+                        let data = exact(data, std::mem::size_of::<$binding>())?;
+                        Value::$binding(unsafe { std::ptr::read_unaligned(data.as_ptr() as *const $binding) })
+                    },)*
+                })
+            }
+        }
+    };
+}
+
+/// String payload, kept raw if it does not decode.
+fn string(data: &[u8], encoding: &'static Encoding, variant: fn(String) -> Value) -> Value {
+    decode_zstring(data, encoding).map_or_else(|_| Value::RawString(data.to_vec()), variant)
+}
+
+/// Payload of exactly `size` bytes.
+fn exact(data: &[u8], size: usize) -> Result<&[u8]> {
+    match data.len() == size {
+        true => Ok(data),
+        false => Err(Error::size(data.len() as u64, size as u64))
+    }
+}
+
+kind! {
+    ATTR, CLAS, CNTO, COED, CRDT, CTDA, DATA, DNAM, DSTD, EFIT, ENIT, HEDR, MGEF, OBND, SCHR, SNDD, SPIT, XNAM
+}
+
+/// How often a field may occur in a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    Once,
+    /// Lists and repeated groups of fields, such as `CTDA` conditions.
+    Many
+}
+
+/// Layout of a field type, or of a range of field types such as `TX00..TX07`.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldDef {
+    pub first: [u8;4],
+    pub last: [u8;4],
+    pub kind: Kind,
+    pub repeat: Repeat
+}
+
+impl FieldDef {
+    pub fn matches(&self, type_id: &[u8;4]) -> bool {
+        self.first <= *type_id && *type_id <= self.last
+    }
+}
+
+/// Fields of a record type in file order. Fields not listed are kept raw.
+#[derive(Debug)]
+pub struct RecordDef {
+    pub type_id: [u8;4],
+    pub fields: &'static [FieldDef]
+}
+
+/// Record layouts of a game.
+#[derive(Debug)]
+pub struct Schema {
+    pub records: &'static [RecordDef],
+    /// Fields of the records without a layout of their own, in no particular
+    /// order.
+    pub shared: &'static [FieldDef]
+}
+
+impl Schema {
+    pub fn record(&self, type_id: &[u8;4]) -> Option<&'static RecordDef> {
+        self.records.iter().find(|record| &record.type_id == type_id)
+    }
+
+    /// Field layouts of a record type.
+    pub fn fields(&self, type_id: &[u8;4]) -> &'static [FieldDef] {
+        self.record(type_id).map_or(self.shared, |record| record.fields)
+    }
+}
+
+/// Type ID spelled by a four letter identifier.
+pub(crate) const fn tag(name: &str) -> [u8;4] {
+    let bytes = name.as_bytes();
+    assert!(bytes.len() == 4, "type IDs have four characters");
+    [bytes[0], bytes[1], bytes[2], bytes[3]]
+}
+
+/// Declare a `Schema`, each record lists `FIELD: Kind` in file order. A kind in
+/// brackets repeats, `TX00..TX07` covers a range of field types and
+/// `Struct(Type)` decodes a struct deriving `EsmField`.
+///
+/// ```ignore
+/// schema! {
+///     records {
+///         GLOB { EDID: ZString, FNAM: U8, FLTV: F32 }
+///         TXST { EDID: ZString, TX00..TX07: ZString, CTDA: [CTDA] }
+///     }
+///     shared { EDID: ZString }
+/// }
+/// ```
+macro_rules! schema {
+    (
+        records { $($record:ident { $($first:ident $(..$last:ident)? : $kind:tt $(($($arg:tt)*))?),* $(,)? })* }
+        $(shared { $($shared:ident : $shared_kind:tt $(($($shared_arg:tt)*))?),* $(,)? })?
+    ) => {
+        $crate::schema::Schema {
+            records: &[$($crate::schema::RecordDef {
+                type_id: $crate::schema::tag(stringify!($record)),
+                fields: &[$($crate::schema::schema!(@field $first $($last)? : $kind $(($($arg)*))?)),*]
+            }),*],
+            shared: &[$($($crate::schema::schema!(@field $shared : $shared_kind $(($($shared_arg)*))?)),*)?]
+        }
+    };
+    (@field $first:ident $($last:ident)? : [$($kind:tt)*]) => {
+        $crate::schema::FieldDef { repeat: $crate::schema::Repeat::Many, ..$crate::schema::schema!(@field $first $($last)? : $($kind)*) }
+    };
+    (@field $first:ident : $($kind:tt)*) => {
+        $crate::schema::schema!(@field $first $first : $($kind)*)
+    };
+    (@field $first:ident $last:ident : $($kind:tt)*) => {
+        $crate::schema::FieldDef {
+            first: $crate::schema::tag(stringify!($first)),
+            last: $crate::schema::tag(stringify!($last)),
+            kind: $crate::schema::schema!(@kind $($kind)*),
+            repeat: $crate::schema::Repeat::Once
+        }
+    };
+    (@kind Struct($ty:ty)) => {
+        $crate::schema::Kind::Struct($crate::field::decode_value::<$ty>)
+    };
+    (@kind $kind:ident $(($len:literal))?) => {
+        $crate::schema::Kind::$kind $(($len))?
+    };
+}
+pub(crate) use schema;
+
+//------------------------------------------------------------------------------
+
+impl<R> ESMParser<R> where R: std::io::Read + std::io::Seek {
+    /// Decode the fields of a record with the registered decoders or else its
+    /// layout in `schema`, keeping a diagnostic for fields repeated against
+    /// their layout or out of its order.
+    /// Going back to an earlier field is in order if it repeats, as in lists of
+    /// `EFID` and `EFIT` pairs.
+    pub(crate) fn parse_schema(&mut self, schema: &Schema, header: &RecordHeader, visitor: &mut dyn Visitor) -> Result<()> {
+        let ordered = schema.record(&header.type_id.0).is_some();
+        let fields = schema.fields(&header.type_id.0);
+        let (record, version) = (header.type_id.0, header.version);
+        let (mut seen, mut last) = (Vec::new(), 0);
+        self.parse_fields(|parser, header| {
+            let type_id = header.type_id.0;
+            if let Some(decoder) = parser.decoders.registered(&record, &type_id).cloned() {
+                let data = parser.read_bytes(parser.field_size(header))?;
+                let strings = parser.localised.then_some(&*parser.strings);
+                return decoder(&FieldContext { header, version, encoding: parser.encoding(), strings }, &data)
+            }
+            let Some(index) = fields.iter().position(|field| field.matches(&type_id)) else {
+                return parser.raw_field(header)
+            };
+            let field = &fields[index];
+            if field.repeat == Repeat::Once {
+                let problem = match seen.contains(&type_id) {
+                    true => Some("field repeated in record"),
+                    false => (ordered && index < last).then_some("field out of order in record")
+                };
+                if let Some(problem) = problem {
+                    let diagnostic = parser.locate(Error::invalid(problem));
+                    parser.diagnostics.push(diagnostic);
+                }
+                seen.push(type_id);
+            }
+            last = index;
+            parser.decode(field.kind, header, version)
+        }, header.size, visitor)
+    }
+}
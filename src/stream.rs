@@ -0,0 +1,99 @@
+//! Parsing from forward only readers.
+
+use crate::ESMParser;
+
+use std::io::{Read, Seek, SeekFrom};
+
+//------------------------------------------------------------------------------
+
+/// Bytes kept behind the read position, enough to peek at a header.
+const WINDOW: usize = 64;
+
+/// Adapter giving a forward only reader the `Seek` the parser needs.
+///
+/// Seeking forward reads and discards, seeking back is limited to the last few
+/// bytes read and the end of the stream is unknown.
+pub struct Forward<R> {
+    inner: R,
+    /// Tail of the data read from `inner`, ending at `end`.
+    window: Vec<u8>,
+    end: u64,
+    position: u64
+}
+
+impl<R: Read> Forward<R> {
+    pub fn new(inner: R) -> Self {
+        Forward { inner, window: Vec::with_capacity(WINDOW * 2), end: 0, position: 0 }
+    }
+
+    pub fn into_inner(self) -> R { self.inner }
+
+    /// Remember the tail of freshly read data.
+    fn keep(&mut self, data: &[u8]) {
+        if data.len() >= WINDOW {
+            self.window.clear();
+            self.window.extend_from_slice(&data[data.len() - WINDOW..]);
+        } else {
+            self.window.extend_from_slice(data);
+            let excess = self.window.len().saturating_sub(WINDOW);
+            self.window.drain(..excess);
+        }
+    }
+}
+
+impl<R: Read> Read for Forward<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let behind = (self.end - self.position) as usize;
+        let n = if behind > 0 { // replay from the window
+            let start = self.window.len() - behind;
+            let n = behind.min(buf.len());
+            buf[..n].copy_from_slice(&self.window[start..start + n]);
+            n
+        } else {
+            let n = self.inner.read(buf)?;
+            self.keep(&buf[..n]);
+            self.end += n as u64;
+            n
+        };
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read> Seek for Forward<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(_) => None
+        };
+        let Some(target) = target.filter(|&target| target + self.window.len() as u64 >= self.end) else {
+            return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "forward only stream"))
+        };
+        if target > self.end { // read and discard
+            let skip = target - self.end;
+            let skipped = std::io::copy(&mut (&mut self.inner).take(skip), &mut std::io::sink())?;
+            self.window.clear();
+            self.end += skipped;
+            if skipped < skip {
+                self.position = self.end;
+                return Err(std::io::ErrorKind::UnexpectedEof.into())
+            }
+        }
+        self.position = target;
+        Ok(target)
+    }
+}
+
+//------------------------------------------------------------------------------
+
+impl<R: Read> ESMParser<Forward<R>> {
+    /// Parse from a reader which cannot seek, such as stdin or a download.
+    ///
+    /// Random access with `get` and `build_index` is not available.
+    pub fn stream(reader: R) -> Self {
+        let mut parser = ESMParser::new(Forward::new(reader));
+        parser.forward = true;
+        parser
+    }
+}
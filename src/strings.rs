@@ -0,0 +1,113 @@
+//! Localised string tables.
+
+use crate::encoding::*;
+use crate::error::{Error, Result};
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+
+//------------------------------------------------------------------------------
+
+/// String table file a localised field is stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringKind {
+    /// Names and other short strings, `.STRINGS`.
+    Strings,
+    /// Descriptions, `.DLSTRINGS`.
+    DLStrings,
+    /// Dialogue responses, `.ILSTRINGS`.
+    ILStrings
+}
+
+impl StringKind {
+    pub const ALL: [StringKind; 3] = [StringKind::Strings, StringKind::DLStrings, StringKind::ILStrings];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            StringKind::Strings => "STRINGS",
+            StringKind::DLStrings => "DLSTRINGS",
+            StringKind::ILStrings => "ILSTRINGS"
+        }
+    }
+
+    /// Table holding the strings of a field type.
+    pub fn of(type_id: &[u8;4]) -> Self {
+        match type_id {
+            b"DESC" | b"CNAM" => StringKind::DLStrings,
+            b"NAM1" => StringKind::ILStrings,
+            _ => StringKind::Strings
+        }
+    }
+}
+
+/// Strings of a single table file keyed by string ID.
+#[derive(Debug, Default)]
+pub struct StringTable(HashMap<u32, String>);
+
+impl StringTable {
+    /// Decode a table file, a directory of `(id, offset)` pairs followed by the
+    /// string data. `.DLSTRINGS` and `.ILSTRINGS` entries are length prefixed.
+    pub fn parse(data: &[u8], kind: StringKind, encoding: &'static Encoding) -> Result<Self> {
+        let u32_at = |offset: usize| -> Result<u32> {
+            let bytes = data.get(offset..offset + 4).ok_or(Error::invalid("truncated string table"))?;
+            Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        };
+        let count = u32_at(0)? as usize;
+        let data_start = count.checked_mul(8).and_then(|size| size.checked_add(8))
+            .ok_or(Error::invalid("truncated string table"))?;
+        let mut strings = HashMap::with_capacity(count.min(data.len() / 8));
+        for i in 0..count {
+            let id = u32_at(8 + i * 8)?;
+            let mut offset = data_start + u32_at(12 + i * 8)? as usize;
+            if kind != StringKind::Strings { offset += 4; } // skip the length, the terminator suffices
+            let string = data.get(offset..).and_then(|tail| CStr::from_bytes_until_nul(tail).ok())
+                .ok_or(Error::invalid("string table entry out of bounds"))?;
+            strings.insert(id, encoding.decode_without_bom_handling(string.to_bytes()).0.into_owned());
+        }
+        Ok(StringTable(strings))
+    }
+
+    pub fn get(&self, id: u32) -> Option<&String> { self.0.get(&id) }
+    pub fn len(&self) -> usize { self.0.len() }
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+}
+
+/// The three string tables of a localised plugin.
+#[derive(Debug, Default)]
+pub struct Strings {
+    tables: [StringTable; 3],
+    language: Option<String>
+}
+
+impl Strings {
+    /// Load `Strings/<plugin>_<language>.*STRINGS` next to the plugin file,
+    /// decoded with `encoding`, see `default_encoding` and
+    /// `ESMParser::load_strings`.
+    pub fn load(plugin: impl AsRef<std::path::Path>, language: &str, encoding: &'static Encoding) -> Result<Self> {
+        let plugin = plugin.as_ref();
+        let stem = plugin.file_stem().ok_or(Error::invalid("plugin path without a file name"))?;
+        let dir = plugin.parent().unwrap_or(std::path::Path::new("")).join("Strings");
+        let mut strings = Strings { language: Some(language.to_owned()), ..Strings::default() };
+        for kind in StringKind::ALL {
+            let name = format!("{}_{}.{}", stem.to_string_lossy(), language, kind.extension());
+            let data = std::fs::read(dir.join(name))?;
+            strings.set(kind, StringTable::parse(&data, kind, encoding)?);
+        }
+        Ok(strings)
+    }
+
+    /// Language the tables were loaded for.
+    pub fn language(&self) -> Option<&str> { self.language.as_deref() }
+
+    pub fn set(&mut self, kind: StringKind, table: StringTable) {
+        self.tables[kind as usize] = table;
+    }
+
+    pub fn table(&self, kind: StringKind) -> &StringTable {
+        &self.tables[kind as usize]
+    }
+
+    pub fn get(&self, kind: StringKind, id: u32) -> Option<&String> {
+        self.table(kind).get(id)
+    }
+}
@@ -0,0 +1,130 @@
+//! Streaming parse events.
+
+use esm_bindings::fo3::*;
+
+use crate::error::Result;
+use crate::model::Value;
+
+use std::io::Write;
+
+//------------------------------------------------------------------------------
+
+/// Receiver of parse events, called in file order.
+///
+/// Every callback defaults to a no-op, so implementations only override the
+/// events they are interested in.
+#[allow(unused_variables)]
+pub trait Visitor {
+    /// A group starts, its children follow until the matching `leave_group`.
+    fn enter_group(&mut self, header: &GroupHeader) -> Result<()> { Ok(()) }
+
+    /// A group ends.
+    fn leave_group(&mut self, header: &GroupHeader) -> Result<()> { Ok(()) }
+
+    /// A record starts, its fields follow until the matching `leave_record`.
+    fn record(&mut self, header: &RecordHeader) -> Result<()> { Ok(()) }
+
+    /// The raw zlib stream of a compressed record, before its fields.
+    fn compressed(&mut self, data: &[u8]) -> Result<()> { Ok(()) }
+
+    /// A record ends.
+    fn leave_record(&mut self, header: &RecordHeader) -> Result<()> { Ok(()) }
+
+    /// A field of the current record was decoded.
+    fn field(&mut self, header: &FieldHeader, value: Value) -> Result<()> { Ok(()) }
+}
+
+/// Visitor ignoring every event.
+impl Visitor for () {}
+
+//------------------------------------------------------------------------------
+
+/// Dump output, writes to stdout unless replaced.
+#[derive(Default)]
+pub struct Sink(Option<Box<dyn Write + Send>>);
+
+impl Sink {
+    pub fn new(output: impl Write + Send + 'static) -> Self {
+        Sink(Some(Box::new(output)))
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match &mut self.0 {
+            Some(output) => output.write(buf),
+            None => std::io::stdout().write(buf)
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.0 {
+            Some(output) => output.flush(),
+            None => std::io::stdout().flush()
+        }
+    }
+}
+
+impl std::fmt::Debug for Sink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(if self.0.is_some() { "Sink" } else { "Sink(stdout)" })
+    }
+}
+
+//------------------------------------------------------------------------------
+
+macro_rules! indentln {
+    ($parser:expr, $($arg:tt)*) => {
+        let indent = " ".repeat($parser.depth() as usize * 2);
+        writeln!($parser.output, "{}{}", indent, format_args!($($arg)*))?;
+    };
+}
+
+/// Human readable text dump of the parse events.
+pub struct Dump<W: Write> {
+    output: W,
+    depth: usize,
+    skip: bool
+}
+
+impl<W: Write> Dump<W> {
+    pub fn new(output: W) -> Self {
+        Dump { output, depth: 0, skip: false }
+    }
+
+    fn depth(&self) -> usize { self.depth }
+}
+
+impl<W: Write> Visitor for Dump<W> {
+    fn enter_group(&mut self, header: &GroupHeader) -> Result<()> {
+        indentln!(self, "{:?}", header);
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave_group(&mut self, _header: &GroupHeader) -> Result<()> {
+        self.depth -= 1;
+        Ok(())
+    }
+
+    fn record(&mut self, header: &RecordHeader) -> Result<()> {
+        self.skip = header.type_id == b"REFR"; // too many to be useful
+        if !self.skip { indentln!(self, "{:?}", header); }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave_record(&mut self, _header: &RecordHeader) -> Result<()> {
+        self.depth -= 1;
+        Ok(())
+    }
+
+    fn field(&mut self, header: &FieldHeader, value: Value) -> Result<()> {
+        if self.skip { return Ok(()) }
+        match value {
+            Value::Unknown(_) => { indentln!(self, "{:?} Unknown field '{}'", header, header.type_id); },
+            value => { indentln!(self, "{:?} {:?}", header, value); }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+//! Plugin serializer.
+
+use esm_bindings::fo3::*;
+
+use crate::encoding::*;
+use crate::error::{Error, Result};
+use crate::field::FieldValue;
+use crate::form_id::FormId;
+use crate::model::*;
+
+use std::io::Write;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+//------------------------------------------------------------------------------
+
+/// Field payload encoding, the inverse of the parser's read functions.
+pub trait Encode {
+    fn encode(&self, out: &mut Vec<u8>);
+}
+
+/// Raw view of a plain data binding.
+fn bytes_of<T: Copy>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, std::mem::size_of::<T>()) }
+}
+
+macro_rules! encode_pod {
+    ($($ty:ty),*) => {
+        $(impl Encode for $ty {
+            fn encode(&self, out: &mut Vec<u8>) { out.extend_from_slice(bytes_of(self)); }
+        })*
+    };
+}
+
+encode_pod!(u8, u16, u32, u64, f32, ATTR, CLAS, CNTO, COED, CRDT, CTDA, DATA, DNAM, DSTD, EFIT, ENIT,
+    HEDR, MGEF, OBND, SCHR, SNDD, SPIT, XNAM);
+
+/// Zero terminated UTF-8 string, the writer encodes plugin strings in the
+/// codepage of the plugin instead.
+impl Encode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_bytes());
+        out.push(0);
+    }
+}
+
+impl Encode for Box<dyn FieldValue> {
+    fn encode(&self, out: &mut Vec<u8>) { self.write_to(out); }
+}
+
+impl Encode for FormId {
+    fn encode(&self, out: &mut Vec<u8>) { self.0.encode(out); }
+}
+
+impl Encode for LocalisedString {
+    fn encode(&self, out: &mut Vec<u8>) { self.id.encode(out); }
+}
+
+impl Encode for Vec<u8> {
+    fn encode(&self, out: &mut Vec<u8>) { out.extend_from_slice(self); }
+}
+
+//------------------------------------------------------------------------------
+
+/// Serializer writing a `Plugin` back to the ESM format.
+///
+/// Record, group and field sizes are recomputed from the encoded contents, so
+/// the header sizes in the model are ignored.
+pub struct Writer<W: Write> {
+    output: W
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(output: W) -> Self {
+        Writer { output }
+    }
+
+    /// Write the `TES4` header followed by every top level group.
+    pub fn write_plugin(&mut self, plugin: &Plugin) -> Result<()> {
+        let mut buffer = Vec::new();
+        encode_record(&plugin.header, plugin, &mut buffer)?;
+        self.output.write_all(&buffer)?;
+        for group in &plugin.groups {
+            buffer.clear();
+            encode_group(group, plugin, &mut buffer)?;
+            self.output.write_all(&buffer)?;
+        }
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+/// Encode a group and its children, patching the group size afterwards.
+fn encode_group(group: &Group, plugin: &Plugin, out: &mut Vec<u8>) -> Result<()> {
+    let start = out.len();
+    out.extend_from_slice(&bytes_of(&group.header)[..plugin.game.header_size()]);
+    for entry in &group.entries {
+        match entry {
+            Entry::Group(group) => encode_group(group, plugin, out)?,
+            Entry::Record(record) => encode_record(record, plugin, out)?
+        }
+    }
+    let size = u32::try_from(out.len() - start).map_err(|_| Error::invalid("group larger than 4 GiB"))?;
+    out[start + 4..start + 8].copy_from_slice(&size.to_le_bytes());
+    Ok(())
+}
+
+/// Encode a record, compressing its fields when flagged.
+fn encode_record(record: &Record, plugin: &Plugin, out: &mut Vec<u8>) -> Result<()> {
+    let mut data = Vec::new();
+    for field in &record.fields {
+        encode_field(field, plugin.encoding, &mut data)?;
+    }
+    let mut header = record.header;
+    if (header.flags & 0x00040000) != 0 {
+        let stream = match &record.compressed {
+            Some(original) if crate::inflate(original, data.len() as u64, data.len() as u64).is_ok_and(|inflated| inflated == data) => {
+                original.clone()
+            },
+            _ => deflate(&data)?
+        };
+        let uncompressed_size = data.len() as u32;
+        data.clear();
+        data.extend_from_slice(&uncompressed_size.to_le_bytes());
+        data.extend_from_slice(&stream);
+    }
+    header.size = u32::try_from(data.len()).map_err(|_| Error::invalid("record larger than 4 GiB"))?;
+    out.extend_from_slice(&bytes_of(&header)[..plugin.game.header_size()]);
+    out.extend_from_slice(&data);
+    Ok(())
+}
+
+/// Encode a field with its recomputed size.
+fn encode_field(field: &Field, encoding: &'static Encoding, out: &mut Vec<u8>) -> Result<()> {
+    let data = match &field.value {
+        Value::ZString(string) | Value::LString(string) => encode_zstring(string, encoding)?,
+        value => {
+            let mut data = Vec::new();
+            value.encode(&mut data);
+            data
+        }
+    };
+    let mut header = field.header;
+    header.size = match u16::try_from(data.len()) {
+        Ok(size) => size,
+        Err(_) => { // the real size goes into a preceding XXXX field
+            let size = u32::try_from(data.len()).map_err(|_| Error::invalid("field larger than 4 GiB"))?;
+            let mut XXXX = field.header;
+            XXXX.type_id.0 = *b"XXXX";
+            XXXX.size = 4;
+            out.extend_from_slice(bytes_of(&XXXX));
+            out.extend_from_slice(&size.to_le_bytes());
+            0
+        }
+    };
+    out.extend_from_slice(bytes_of(&header));
+    out.extend_from_slice(&data);
+    Ok(())
+}
+
+/// Compress a buffer into a Zlib stream.
+fn deflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).map_err(Error::zlib)?;
+    encoder.finish().map_err(Error::zlib)
+}